@@ -14,10 +14,16 @@ pub enum AudioCommand {
     BallPaddleBounce,
     BallSturdyBlockBounce,
     BallDrop,
+    Explosion,
+
+    PowerUpPickup,
+    LaserFire,
 
     LevelStart,
     LevelWin,
     LevelLost,
+    ExtraLife,
+    TallyRow,
 }
 
 pub fn execute_audio_command_buffer(
@@ -41,6 +47,17 @@ pub fn execute_audio_command_buffer(
                     .rl_audio_device
                     .play_sound(&audio.sounds[random_explosion]);
             }
+            AudioCommand::Explosion => {
+                let explosion_variants = [
+                    SoundEffect::ExplosionOne as usize,
+                    SoundEffect::ExplosionTwo as usize,
+                    SoundEffect::ExplosionThree as usize,
+                ];
+                let random_explosion = explosion_variants[rng.gen_range(0..3)];
+                audio
+                    .rl_audio_device
+                    .play_sound(&audio.sounds[random_explosion]);
+            }
             AudioCommand::BallWallBounce => {
                 audio
                     .rl_audio_device
@@ -71,11 +88,31 @@ pub fn execute_audio_command_buffer(
                     .rl_audio_device
                     .play_sound(&audio.sounds[SoundEffect::BallDrop as usize]);
             }
+            AudioCommand::PowerUpPickup => {
+                audio
+                    .rl_audio_device
+                    .play_sound(&audio.sounds[SoundEffect::SuperConfirm as usize]);
+            }
+            AudioCommand::LaserFire => {
+                audio
+                    .rl_audio_device
+                    .play_sound(&audio.sounds[SoundEffect::SmallLaser as usize]);
+            }
             AudioCommand::LevelLost => {
                 audio
                     .rl_audio_device
                     .play_sound(&audio.sounds[SoundEffect::LevelLost as usize]);
             }
+            AudioCommand::TallyRow => {
+                audio
+                    .rl_audio_device
+                    .play_sound(&audio.sounds[SoundEffect::BallHitPaddle as usize]);
+            }
+            AudioCommand::ExtraLife => {
+                audio
+                    .rl_audio_device
+                    .play_sound(&audio.sounds[SoundEffect::Confirm as usize]);
+            }
         }
     }
 }
@@ -0,0 +1,136 @@
+use std::path::Path;
+
+use hecs::World;
+
+use hecs_arkanoid::{
+    checksum::get_checksum,
+    headless::{describe_world, run_headless, run_replay_headless, InputScript},
+    physics_backend::PhysicsBackendKind,
+    replay::Replay,
+    state::State,
+};
+
+const USAGE: &str = "usage: headless [--seed N] [--frames N] [--level N] [--script FILE] \
+    [--physics rapier|aabb] [--verify]";
+
+struct Args {
+    seed: u64,
+    frames: u32,
+    level: u32,
+    script_path: Option<String>,
+    /// save the run as a replay
+    record_path: Option<String>,
+    /// play a replay back instead, its seed, level and physics override the others
+    replay_path: Option<String>,
+    /// run twice and fail if the two runs end differently
+    verify: bool,
+    physics_backend: PhysicsBackendKind,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        seed: 0,
+        frames: 3600,
+        level: 1,
+        script_path: None,
+        record_path: None,
+        replay_path: None,
+        verify: false,
+        physics_backend: PhysicsBackendKind::default(),
+    };
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--verify" => args.verify = true,
+            "--seed" | "--frames" | "--level" | "--script" | "--record" | "--replay"
+            | "--physics" => {
+                let value = argv
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                let bad_number = |_| format!("{} expects a number, found '{}'", arg, value);
+                match arg.as_str() {
+                    "--seed" => args.seed = value.parse().map_err(bad_number)?,
+                    "--frames" => args.frames = value.parse().map_err(bad_number)?,
+                    "--level" => args.level = value.parse().map_err(bad_number)?,
+                    "--script" => args.script_path = Some(value),
+                    "--record" => args.record_path = Some(value),
+                    "--physics" => {
+                        args.physics_backend = PhysicsBackendKind::from_name(&value)
+                            .ok_or_else(|| format!("unknown physics '{}'", value))?
+                    }
+                    _ => args.replay_path = Some(value),
+                }
+            }
+            _ => return Err(format!("unknown argument '{}'", arg)),
+        }
+    }
+    Ok(args)
+}
+
+/// the world at the end, and anything the ball watchdog had to fix along the way
+fn run(args: &Args, script: Option<&InputScript>) -> (String, Vec<String>) {
+    let mut state = State::new_seeded(args.seed);
+    state.set_physics_backend(args.physics_backend);
+    let mut ecs = World::new();
+
+    if let Some(path) = &args.replay_path {
+        let played = Replay::load(Path::new(path))
+            .and_then(|replay| run_replay_headless(&mut ecs, &mut state, replay));
+        if let Err(e) = played {
+            println!("Error playing replay {}: {}", path, e);
+            std::process::exit(2);
+        }
+        return (describe_world(&ecs, &state), describe_watchdog(&state));
+    }
+
+    let recording = run_headless(&mut ecs, &mut state, args.level, args.frames, script);
+    if let Some(path) = &args.record_path {
+        if let Err(e) = recording.save(Path::new(path)) {
+            println!("Error saving replay {}: {}", path, e);
+        }
+    }
+    (describe_world(&ecs, &state), describe_watchdog(&state))
+}
+
+fn describe_watchdog(state: &State) -> Vec<String> {
+    state
+        .watchdog_events
+        .iter()
+        .map(|event| format!("ball watchdog, {}", event))
+        .collect()
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        println!("{}\n{}", e, USAGE);
+        std::process::exit(2);
+    });
+
+    let script = args.script_path.as_ref().map(|path| {
+        let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+            println!("Error reading script {}: {}", path, e);
+            std::process::exit(2);
+        });
+        InputScript::parse(&text).unwrap_or_else(|e| {
+            println!("Error in script {}: {}", path, e);
+            std::process::exit(2);
+        })
+    });
+
+    let (world, watchdog) = run(&args, script.as_ref());
+    for line in watchdog.iter() {
+        println!("{}", line);
+    }
+    println!("{}", world);
+    println!("checksum {:016x}", get_checksum(&world));
+    println!("watchdog fixes {}", watchdog.len());
+
+    if args.verify {
+        let (again, _) = run(&args, script.as_ref());
+        if again != world {
+            println!("runs diverged, second run ended with:\n{}", again);
+            std::process::exit(1);
+        }
+        println!("deterministic");
+    }
+}
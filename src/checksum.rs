@@ -0,0 +1,6 @@
+/// fnv-1a, stable across rust versions unlike the std hasher
+pub fn get_checksum(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
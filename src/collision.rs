@@ -0,0 +1,76 @@
+use glam::Vec2;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+impl Aabb {
+    pub fn new(pos: Vec2, size: Vec2) -> Aabb {
+        Aabb { pos, size }
+    }
+
+    pub fn from_center(center: Vec2, size: Vec2) -> Aabb {
+        Aabb {
+            pos: center - size / 2.0,
+            size,
+        }
+    }
+
+    pub fn get_bounds(&self) -> Tlbr {
+        Tlbr {
+            tl: self.pos,
+            br: self.pos + self.size,
+        }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        let self_tlbr = self.get_bounds();
+        let other_tlbr = other.get_bounds();
+        self_tlbr.intersects(&other_tlbr)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.pos + self.size / 2.0
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tlbr {
+    pub tl: Vec2,
+    pub br: Vec2,
+}
+
+impl Tlbr {
+    pub fn new(tl: Vec2, br: Vec2) -> Tlbr {
+        Tlbr { tl, br }
+    }
+
+    pub fn get_aabb(&self) -> Aabb {
+        Aabb {
+            pos: self.tl,
+            size: self.br - self.tl,
+        }
+    }
+
+    /// touching edges count
+    pub fn intersects(&self, other: &Tlbr) -> bool {
+        !(self.tl.x > other.br.x
+            || self.br.x < other.tl.x
+            || self.tl.y > other.br.y
+            || self.br.y < other.tl.y)
+    }
+
+    /// touching edges don't count
+    pub fn overlaps(&self, other: &Tlbr) -> bool {
+        !(self.tl.x >= other.br.x
+            || self.br.x <= other.tl.x
+            || self.tl.y >= other.br.y
+            || self.br.y <= other.tl.y)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.tl + self.br) / 2.0
+    }
+}
@@ -1,6 +1,11 @@
+use std::collections::VecDeque;
+
 use glam::Vec2;
 use hecs::Entity;
 use raylib::prelude::Color;
+use strum_macros::EnumIter;
+
+use crate::physics_backend::BodyHandle;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct CTransform {
@@ -13,10 +18,24 @@ pub struct Player;
 pub struct Ball;
 pub struct Bouncy;
 
+/// how fast this ball goes and how far along its speed ups it is
+/// dies with the ball, so a newly served ball always starts from the level's base speed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BallSpeed {
+    /// pixels per step
+    pub speed: f32,
+    pub paddle_hits: u32,
+    pub reached_top_wall: bool,
+    pub reached_back_rows: bool,
+}
+
+pub struct Bullet;
+
 pub struct InputControlled;
 
 pub struct Block {
     pub color: Color,
+    pub color_index: u32,
 }
 
 pub struct Health {
@@ -50,6 +69,11 @@ pub struct Score {
     pub score: u32,
 }
 
+/// consecutive block hits since the owner's paddle was last touched
+pub struct Combo {
+    pub hits: u32,
+}
+
 pub struct OwnedBy {
     pub owner: Entity,
 }
@@ -66,26 +90,70 @@ pub struct GrabZone {
 
 pub struct Attachable;
 
+/// the ball goes straight through breakable blocks, breaking them as it goes
+pub struct Piercing;
+
+/// how long the ball has stayed within a thin horizontal band, see ball_watchdog
+pub struct LoopWatch {
+    pub band_y: f32,
+    pub frames: u32,
+}
+
+/// where the entity was over the last few steps, oldest first
+pub struct PositionHistory {
+    pub positions: VecDeque<Vec2>,
+}
+
 pub struct WantsToGoTo {
     pub pos: Vec2,
 }
 
 pub struct LookAt {
     pub entity: Entity,
+    /// unit vector toward the entity, kept here since physics resets every body's rot
+    pub facing: Vec2,
 }
 
 #[derive(Clone, Copy)]
 pub struct Enemy;
 
+/// an opening in the top wall that enemies drift in through
+pub struct EnemyGate;
+
+/// the final level's big multi hit enemy, its hp is in Health
+pub struct Boss {
+    pub max_hp: u32,
+    /// frames left showing the hit flash
+    pub flash_frames: u32,
+}
+
+/// fired by the boss, costs a life if it touches the paddle
+pub struct BossProjectile;
+
+/// the blast left by a bomb ball, centered on its CTransform
+pub struct Explosion {
+    pub radius: f32,
+    pub frames_left: u32,
+}
+
 pub struct Wall;
 pub struct BallEater;
 
-pub struct HasRigidBody;
+/// the segment of the right wall that opens while Break is active
+pub struct WarpGate {
+    pub open: bool,
+}
+
+/// the handle of the entity's body, see PhysicsBackend::insert_rigid_body
+pub struct HasRigidBody {
+    pub handle: BodyHandle,
+}
 pub struct HasSensor;
 
 pub struct VelocityManaged;
 pub struct PositionManaged;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
 pub enum PowerUpType {
     Enlarge,
     Shrink,
@@ -97,8 +165,24 @@ pub enum PowerUpType {
 
     Lasers,
     BombBall,
+    PiercingBall,
+
+    Catch,
+
+    Break,
 }
 
 pub struct PowerUp {
     pub power_up_type: PowerUpType,
 }
+
+#[derive(Clone, Copy, Debug)]
+pub struct ActivePowerUp {
+    pub power_up_type: PowerUpType,
+    pub frames_left: u32,
+    pub stacks: u32,
+}
+
+pub struct ActivePowerUps {
+    pub active: Vec<ActivePowerUp>,
+}
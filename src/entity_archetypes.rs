@@ -1,19 +1,29 @@
+use std::collections::VecDeque;
+
 use glam::Vec2;
 use hecs::{Entity, World};
-use nalgebra::vector;
-use rapier2d::prelude::{
-    ActiveEvents, ColliderBuilder, InteractionGroups, Point, RigidBodyBuilder,
-};
 use raylib::prelude::Color;
 
 use crate::{
     components::{
-        Ball, BallEater, Block, Bouncy, CTransform, HasRigidBody, Health, InputControlled, OwnedBy,
-        Paddle, Physics, Player, PositionManaged, PowerUp, PowerUpType, Shape, StrongBlock,
-        VelocityManaged, Wall,
+        ActivePowerUps, Attachable, Ball, BallEater, Block, Boss, BossProjectile, Bouncy, Bullet,
+        CTransform, Combo, Enemy, EnemyGate, Explosion, FreeToLeavePlayField, Health,
+        InputControlled, LookAt, LoopWatch, OwnedBy, Paddle, Physics, Player, PositionHistory,
+        PositionManaged, PowerUp, PowerUpType, Score, Shape, StrongBlock, VelocityManaged, Wall,
+        WantsToGoTo, WarpGate,
     },
-    physics_engine::p2m,
+    level_data,
+    physics_backend::{BodyDesc, BodyKind},
     state::State,
+    systems::playing::{
+        ball_speed::new_ball_speed,
+        bomb_ball::EXPLOSION_FRAMES,
+        boss::BOSS_FIRE_INTERVAL,
+        enemy_behaviour::ENEMY_SPAWN_INTERVAL,
+        lasers::LASER_FIRE_INTERVAL,
+        warp::{WARP_GATE_HEIGHT, WARP_GATE_TOP},
+    },
+    timer::{BossFireTimer, EnemySpawnTimer, LaserFireTimer},
     DIMS,
 };
 
@@ -36,27 +46,13 @@ pub fn spawn_walls(ecs: &mut World, state: &mut State) {
         },
         shape,
         Wall,
-        HasRigidBody,
     ));
     let center = pos + shape.dims / 2.0;
-    let top_wall_collider =
-        ColliderBuilder::cuboid(p2m(shape.dims.x) / 2.0, p2m(shape.dims.y) / 2.0)
-            .restitution(1.0)
-            .friction(0.0)
-            .build();
-    let top_wall_rigid_body = RigidBodyBuilder::fixed()
-        .translation(vector![p2m(center.x), p2m(center.y)])
-        .can_sleep(false)
-        .build();
-    let top_wall_body_handle = state.physics.rigid_body_set.insert(top_wall_rigid_body);
-    state.physics.collider_set.insert_with_parent(
-        top_wall_collider,
-        top_wall_body_handle,
-        &mut state.physics.rigid_body_set,
+    state.physics.insert_rigid_body(
+        ecs,
+        top_wall,
+        BodyDesc::new(BodyKind::Fixed, center, shape.dims),
     );
-    state
-        .physics
-        .set_rigid_body_mapping(top_wall, top_wall_body_handle);
 
     // bottom wall
     let pos = Vec2::new(0.0, DIMS.y as f32 - 1.0);
@@ -73,28 +69,14 @@ pub fn spawn_walls(ecs: &mut World, state: &mut State) {
         },
         shape,
         Wall,
-        HasRigidBody,
         BallEater,
     ));
     let center = pos + shape.dims / 2.0;
-    let bottom_wall_collider =
-        ColliderBuilder::cuboid(p2m(shape.dims.x) / 2.0, p2m(shape.dims.y) / 2.0)
-            .restitution(1.0)
-            .friction(0.0)
-            .build();
-    let bottom_wall_rigid_body = RigidBodyBuilder::fixed()
-        .translation(vector![p2m(center.x), p2m(center.y)])
-        .can_sleep(false)
-        .build();
-    let bottom_wall_body_handle = state.physics.rigid_body_set.insert(bottom_wall_rigid_body);
-    state.physics.collider_set.insert_with_parent(
-        bottom_wall_collider,
-        bottom_wall_body_handle,
-        &mut state.physics.rigid_body_set,
+    state.physics.insert_rigid_body(
+        ecs,
+        bottom_wall,
+        BodyDesc::new(BodyKind::Fixed, center, shape.dims),
     );
-    state
-        .physics
-        .set_rigid_body_mapping(bottom_wall, bottom_wall_body_handle);
 
     // left wall
     let pos = Vec2::new(-wall_thickness + 1.0, 0.0);
@@ -111,65 +93,38 @@ pub fn spawn_walls(ecs: &mut World, state: &mut State) {
         },
         shape,
         Wall,
-        HasRigidBody,
     ));
     let center = pos + shape.dims / 2.0;
-    let left_wall_collider =
-        ColliderBuilder::cuboid(p2m(shape.dims.x) / 2.0, p2m(shape.dims.y) / 2.0)
-            .restitution(1.0)
-            .friction(0.0)
-            .build();
-    let left_wall_rigid_body = RigidBodyBuilder::fixed()
-        .translation(vector![p2m(center.x), p2m(center.y)])
-        .can_sleep(false)
-        .build();
-    let left_wall_body_handle = state.physics.rigid_body_set.insert(left_wall_rigid_body);
-    state.physics.collider_set.insert_with_parent(
-        left_wall_collider,
-        left_wall_body_handle,
-        &mut state.physics.rigid_body_set,
+    state.physics.insert_rigid_body(
+        ecs,
+        left_wall,
+        BodyDesc::new(BodyKind::Fixed, center, shape.dims),
     );
-    state
-        .physics
-        .set_rigid_body_mapping(left_wall, left_wall_body_handle);
 
-    // right wall
-    let pos = Vec2::new(DIMS.x as f32 - 1.0, 0.0);
-    let shape = Shape {
-        dims: Vec2 {
-            x: wall_thickness,
-            y: DIMS.y as f32,
-        },
-    };
-    let right_wall = ecs.spawn((
-        CTransform {
-            pos,
-            rot: Vec2::new(0.0, 0.0),
-        },
-        shape,
-        Wall,
-        HasRigidBody,
-        // PositionManaged,
-    ));
-    let center = pos + shape.dims / 2.0;
-    let right_wall_collider =
-        ColliderBuilder::cuboid(p2m(shape.dims.x) / 2.0, p2m(shape.dims.y) / 2.0)
-            .restitution(1.0)
-            .friction(0.0)
-            .build();
-    let right_wall_rigid_body = RigidBodyBuilder::fixed()
-        .translation(vector![p2m(center.x), p2m(center.y)])
-        .can_sleep(false)
-        .build();
-    let right_wall_body_handle = state.physics.rigid_body_set.insert(right_wall_rigid_body);
-    state.physics.collider_set.insert_with_parent(
-        right_wall_collider,
-        right_wall_body_handle,
-        &mut state.physics.rigid_body_set,
+    // right wall, in segments around the warp gate at the paddle's height
+    let x = DIMS.x as f32 - 1.0;
+    let gate_bottom = WARP_GATE_TOP + WARP_GATE_HEIGHT;
+    spawn_wall_segment(
+        ecs,
+        state,
+        Vec2::new(x, 0.0),
+        Vec2::new(wall_thickness, WARP_GATE_TOP),
     );
-    state
-        .physics
-        .set_rigid_body_mapping(right_wall, right_wall_body_handle);
+    let warp_gate = spawn_wall_segment(
+        ecs,
+        state,
+        Vec2::new(x, WARP_GATE_TOP),
+        Vec2::new(wall_thickness, WARP_GATE_HEIGHT),
+    );
+    let _ = ecs.insert_one(warp_gate, WarpGate { open: false });
+    spawn_wall_segment(
+        ecs,
+        state,
+        Vec2::new(x, gate_bottom),
+        Vec2::new(wall_thickness, DIMS.y as f32 - gate_bottom),
+    );
+
+    spawn_enemy_gates(ecs);
 
     // middle wall
     // let right_wall = ecs.spawn((
@@ -210,8 +165,31 @@ pub fn spawn_walls(ecs: &mut World, state: &mut State) {
     //     .set_rigid_body_mapping(right_wall, right_wall_body_handle);
 }
 
+/// a plain fixed wall, returned so it can be given more components
+pub fn spawn_wall_segment(ecs: &mut World, state: &mut State, pos: Vec2, dims: Vec2) -> Entity {
+    let wall = ecs.spawn((
+        CTransform {
+            pos,
+            rot: Vec2::new(0.0, 0.0),
+        },
+        Shape { dims },
+        Wall,
+    ));
+    let center = pos + dims / 2.0;
+    state
+        .physics
+        .insert_rigid_body(ecs, wall, BodyDesc::new(BodyKind::Fixed, center, dims));
+    wall
+}
+
 pub const BALL_SHAPE: Vec2 = Vec2::new(4.0, 4.0);
-pub fn spawn_ball(ecs: &mut World, state: &mut State, pos: Vec2, vel: Vec2, owner: Entity) {
+pub fn spawn_ball(
+    ecs: &mut World,
+    state: &mut State,
+    pos: Vec2,
+    vel: Vec2,
+    owner: Entity,
+) -> Entity {
     let ball_entity = ecs.spawn((
         Ball,
         CTransform {
@@ -219,39 +197,49 @@ pub fn spawn_ball(ecs: &mut World, state: &mut State, pos: Vec2, vel: Vec2, owne
             rot: Vec2::new(0.0, 0.0),
         },
         Physics { vel, rot_vel: 0.0 },
+        new_ball_speed(state),
         OwnedBy { owner },
         Shape { dims: BALL_SHAPE },
         Bouncy,
-        HasRigidBody,
+        Attachable,
         VelocityManaged,
+        LoopWatch {
+            band_y: pos.y,
+            frames: 0,
+        },
     ));
-    // let ball_collider = ColliderBuilder::ball(p2m(8.0) / 2.0)
-    let ball_collider = ColliderBuilder::cuboid(p2m(BALL_SHAPE.x) / 2.0, p2m(BALL_SHAPE.y) / 2.0)
-        // let ball_collider = ColliderBuilder::ball(p2m(BALL_SHAPE.x) / 2.0)
-        .restitution(1.0)
-        .friction(0.0)
-        .mass(0.0001)
-        .active_events(ActiveEvents::COLLISION_EVENTS)
-        .collision_groups(InteractionGroups::new(0b0001.into(), 0b0001.into()))
-        .build();
-    let ball_rigid_body = RigidBodyBuilder::dynamic()
-        .translation(vector![p2m(pos.x), p2m(pos.y)])
-        .linvel(vector![p2m(vel.x), p2m(vel.y)])
-        .lock_rotations()
-        .linear_damping(0.0)
-        .angular_damping(0.0)
-        .can_sleep(false)
-        .ccd_enabled(true)
-        .build();
-    let ball_body_handle = state.physics.rigid_body_set.insert(ball_rigid_body);
-    state.physics.collider_set.insert_with_parent(
-        ball_collider,
-        ball_body_handle,
-        &mut state.physics.rigid_body_set,
-    );
+    let ball_body = BodyDesc::new(BodyKind::Dynamic, pos, BALL_SHAPE)
+        .vel(vel)
+        .groups(0b0001, 0b0001)
+        .events()
+        .ccd();
+    state.physics.insert_rigid_body(ecs, ball_entity, ball_body);
+    ball_entity
+}
+
+pub const BULLET_SHAPE: Vec2 = Vec2::new(1.0, 4.0);
+pub fn spawn_bullet(ecs: &mut World, state: &mut State, pos: Vec2, vel: Vec2, owner: Entity) {
+    let bullet_entity = ecs.spawn((
+        Bullet,
+        CTransform {
+            pos,
+            rot: Vec2::new(0.0, 0.0),
+        },
+        Physics { vel, rot_vel: 0.0 },
+        OwnedBy { owner },
+        Shape { dims: BULLET_SHAPE },
+        VelocityManaged,
+    ));
+    // sensor on the bullet channel, so it passes through balls and the paddle but hits blocks
+    let bullet_body = BodyDesc::new(BodyKind::Dynamic, pos + BULLET_SHAPE / 2.0, BULLET_SHAPE)
+        .vel(vel)
+        .groups(0b0100, 0b0100)
+        .sensor()
+        .events()
+        .ccd();
     state
         .physics
-        .set_rigid_body_mapping(ball_entity, ball_body_handle);
+        .insert_rigid_body(ecs, bullet_entity, bullet_body);
 }
 
 pub fn spawn_block(
@@ -259,47 +247,29 @@ pub fn spawn_block(
     state: &mut State,
     pos: Vec2,
     shape: Vec2,
-    color: Color,
+    color_index: u32,
     hp: u32,
     ball_unbreakable: bool,
 ) {
+    let color = level_data::RL_COLOR_PALETTE[color_index as usize];
     let block_entity = ecs.spawn((
         CTransform {
             pos,
             rot: Vec2::new(0.0, 1.0),
         },
         Shape { dims: shape },
-        Block { color },
+        Block { color, color_index },
         Health { hp },
-        HasRigidBody,
     ));
     if ball_unbreakable {
         ecs.insert_one(block_entity, StrongBlock).unwrap();
     }
 
-    let block_collider = ColliderBuilder::cuboid(p2m(shape.x) / 2.0, p2m(shape.y) / 2.0)
-        .restitution(1.0)
-        .friction(0.0)
-        .collision_groups(InteractionGroups::new(0b0101.into(), 0b0101.into()))
-        .build();
-    let block_rigid_body = RigidBodyBuilder::fixed()
-        .translation(vector![
-            p2m(pos.x + shape.x / 2.0),
-            p2m(pos.y + shape.y / 2.0)
-        ])
-        .ccd_enabled(true)
-        .can_sleep(false)
-        .build();
-
-    let block_body_handle = state.physics.rigid_body_set.insert(block_rigid_body);
-    state.physics.collider_set.insert_with_parent(
-        block_collider,
-        block_body_handle,
-        &mut state.physics.rigid_body_set,
-    );
+    let block_body =
+        BodyDesc::new(BodyKind::Fixed, pos + shape / 2.0, shape).groups(0b0101, 0b0101);
     state
         .physics
-        .set_rigid_body_mapping(block_entity, block_body_handle);
+        .insert_rigid_body(ecs, block_entity, block_body);
 }
 
 pub fn spawn_paddle(
@@ -321,77 +291,200 @@ pub fn spawn_paddle(
         InputControlled,
         Player,
         Paddle { size: 1 },
+        PositionHistory {
+            positions: VecDeque::new(),
+        },
+        ActivePowerUps { active: Vec::new() },
+        LaserFireTimer::new(LASER_FIRE_INTERVAL, 0),
         Shape { dims: shape },
-        HasRigidBody,
         PositionManaged,
     ));
 
-    let paddle_collider = ColliderBuilder::cuboid(p2m(shape.x) / 2.0, p2m(shape.y) / 2.0)
-        .restitution(1.0)
-        .collision_groups(InteractionGroups::new(0b0011.into(), 0b0011.into()))
-        .build();
-    let paddle_rigid_body = RigidBodyBuilder::kinematic_position_based()
-        .translation(vector![
-            p2m(pos.x + shape.x / 2.0),
-            p2m(pos.y + shape.y / 2.0)
-        ])
-        // .ccd_enabled(true)
-        .can_sleep(false)
-        .build();
-
-    let paddle_body_handle = state.physics.rigid_body_set.insert(paddle_rigid_body);
-    state.physics.collider_set.insert_with_parent(
-        paddle_collider,
-        paddle_body_handle,
-        &mut state.physics.rigid_body_set,
-    );
+    let paddle_body =
+        BodyDesc::new(BodyKind::KinematicPosition, pos + shape / 2.0, shape).groups(0b0011, 0b0011);
     state
         .physics
-        .set_rigid_body_mapping(paddle_entity, paddle_body_handle);
+        .insert_rigid_body(ecs, paddle_entity, paddle_body);
+
+    // the players score lives on its own entity, owned by the paddle
+    ecs.spawn((
+        Score {
+            owner: paddle_entity,
+            score: state.carried_score,
+        },
+        Combo { hits: 0 },
+    ));
     paddle_entity
 }
 
-// pub fn spawn_powerup(
-//     ecs: &mut World,
-//     state: &mut State,
-//     pos: Vec2,
-//     shape: Vec2,
-//     power_up_type: PowerUpType,
-// ) -> Entity {
-//     let power_up_entity = ecs.spawn((
-//         CTransform {
-//             pos,
-//             rot: Vec2::new(0.0, 0.0),
-//         },
-//         Physics {
-//             vel: Vec2::new(0.0, -2.0),
-//             rot_vel: 0.0,
-//         },
-//         Shape { dims: shape },
-//         PowerUp { power_up_type },
-//     ));
+pub const ENEMY_GATE_SHAPE: Vec2 = Vec2::new(16.0, 2.0);
+/// one gate a quarter of the way in from each side, taking turns
+pub fn spawn_enemy_gates(ecs: &mut World) {
+    for (i, x) in [DIMS.x as f32 * 0.25, DIMS.x as f32 * 0.75]
+        .into_iter()
+        .enumerate()
+    {
+        let initial_countdown = ENEMY_SPAWN_INTERVAL / 2 * (i as u32 + 1);
+        ecs.spawn((
+            CTransform {
+                pos: Vec2::new(x - ENEMY_GATE_SHAPE.x / 2.0, 0.0),
+                rot: Vec2::new(0.0, 0.0),
+            },
+            Shape {
+                dims: ENEMY_GATE_SHAPE,
+            },
+            EnemyGate,
+            EnemySpawnTimer::new(ENEMY_SPAWN_INTERVAL, initial_countdown),
+        ));
+    }
+}
+
+pub const ENEMY_SHAPE: Vec2 = Vec2::new(8.0, 8.0);
+pub fn spawn_enemy(ecs: &mut World, state: &mut State, pos: Vec2, target: Vec2) -> Entity {
+    let enemy_entity = ecs.spawn((
+        CTransform {
+            pos,
+            rot: Vec2::new(0.0, 0.0),
+        },
+        Physics {
+            vel: Vec2::ZERO,
+            rot_vel: 0.0,
+        },
+        Shape { dims: ENEMY_SHAPE },
+        Enemy,
+        WantsToGoTo { pos: target },
+        VelocityManaged,
+        FreeToLeavePlayField,
+    ));
+
+    // solid to the ball and the paddle, but floats through blocks and walls
+    let enemy_body = BodyDesc::new(
+        BodyKind::KinematicVelocity,
+        pos + ENEMY_SHAPE / 2.0,
+        ENEMY_SHAPE,
+    )
+    .groups(0b0011, 0b0011)
+    .events()
+    .kinematic_contacts();
+    state
+        .physics
+        .insert_rigid_body(ecs, enemy_entity, enemy_body);
+    enemy_entity
+}
+
+pub const BOSS_SHAPE: Vec2 = Vec2::new(40.0, 48.0);
+/// the boss sits top center and keeps an eye on the paddle
+pub fn spawn_boss(ecs: &mut World, state: &mut State, hp: u32, paddle: Option<Entity>) -> Entity {
+    let pos = Vec2::new((DIMS.x as f32 - BOSS_SHAPE.x) / 2.0, 12.0);
+    let boss_entity = ecs.spawn((
+        CTransform {
+            pos,
+            rot: Vec2::new(0.0, 1.0),
+        },
+        Shape { dims: BOSS_SHAPE },
+        Boss {
+            max_hp: hp,
+            flash_frames: 0,
+        },
+        Health { hp },
+        BossFireTimer::new(BOSS_FIRE_INTERVAL, BOSS_FIRE_INTERVAL),
+    ));
+    if let Some(paddle) = paddle {
+        let _ = ecs.insert_one(
+            boss_entity,
+            LookAt {
+                entity: paddle,
+                facing: Vec2::Y,
+            },
+        );
+    }
+
+    // on the block channel, so both the ball and laser bolts hit it
+    let boss_body =
+        BodyDesc::new(BodyKind::Fixed, pos + BOSS_SHAPE / 2.0, BOSS_SHAPE).groups(0b0101, 0b0101);
+    state.physics.insert_rigid_body(ecs, boss_entity, boss_body);
+    boss_entity
+}
+
+/// purely visual, it has no body and goes once its frames run out
+pub fn spawn_explosion(ecs: &mut World, center: Vec2, radius: f32) -> Entity {
+    ecs.spawn((
+        CTransform {
+            pos: center,
+            rot: Vec2::new(0.0, 0.0),
+        },
+        Explosion {
+            radius,
+            frames_left: EXPLOSION_FRAMES,
+        },
+    ))
+}
+
+pub const BOSS_PROJECTILE_SHAPE: Vec2 = Vec2::new(4.0, 4.0);
+pub fn spawn_boss_projectile(ecs: &mut World, state: &mut State, pos: Vec2, vel: Vec2) {
+    let projectile_entity = ecs.spawn((
+        CTransform {
+            pos,
+            rot: Vec2::new(0.0, 0.0),
+        },
+        Physics { vel, rot_vel: 0.0 },
+        Shape {
+            dims: BOSS_PROJECTILE_SHAPE,
+        },
+        BossProjectile,
+        VelocityManaged,
+        FreeToLeavePlayField,
+    ));
 
-//     let paddle_collider = ColliderBuilder::cuboid(p2m(shape.x) / 2.0, p2m(shape.y) / 2.0)
-//         .restitution(1.0)
-//         .collision_groups(InteractionGroups::new(0b0011.into(), 0b0011.into()))
-//         .build();
-//     let paddle_rigid_body = RigidBodyBuilder::kinematic_position_based()
-//         .translation(vector![
-//             p2m(pos.x + shape.x / 2.0),
-//             p2m(pos.y + shape.y / 2.0)
-//         ])
-//         // .ccd_enabled(true)
-//         .can_sleep(false)
-//         .build();
+    // sensor on the pickup channel, so it passes through everything but the paddle
+    let projectile_body = BodyDesc::new(
+        BodyKind::Dynamic,
+        pos + BOSS_PROJECTILE_SHAPE / 2.0,
+        BOSS_PROJECTILE_SHAPE,
+    )
+    .vel(vel)
+    .groups(0b0010, 0b0010)
+    .sensor()
+    .events();
+    state
+        .physics
+        .insert_rigid_body(ecs, projectile_entity, projectile_body);
+}
 
-//     let paddle_body_handle = state.physics.rigid_body_set.insert(paddle_rigid_body);
-//     state.physics.collider_set.insert_with_parent(
-//         paddle_collider,
-//         paddle_body_handle,
-//         &mut state.physics.rigid_body_set,
-//     );
-//     state
-//         .physics
-//         .set_rigid_body_mapping(paddle_entity, paddle_body_handle);
-//     paddle_entity
-// }
+pub const POWER_UP_SHAPE: Vec2 = Vec2::new(12.0, 5.0);
+pub fn spawn_powerup(
+    ecs: &mut World,
+    state: &mut State,
+    pos: Vec2,
+    vel: Vec2,
+    power_up_type: PowerUpType,
+) -> Entity {
+    let power_up_entity = ecs.spawn((
+        CTransform {
+            pos,
+            rot: Vec2::new(0.0, 0.0),
+        },
+        Physics { vel, rot_vel: 0.0 },
+        Shape {
+            dims: POWER_UP_SHAPE,
+        },
+        PowerUp { power_up_type },
+        VelocityManaged,
+        FreeToLeavePlayField,
+    ));
+
+    // sensor on the pickup channel, so it only reports overlaps with the paddle and walls
+    let power_up_body = BodyDesc::new(
+        BodyKind::Dynamic,
+        pos + POWER_UP_SHAPE / 2.0,
+        POWER_UP_SHAPE,
+    )
+    .vel(vel)
+    .groups(0b0010, 0b0010)
+    .sensor()
+    .events();
+    state
+        .physics
+        .insert_rigid_body(ecs, power_up_entity, power_up_body);
+    power_up_entity
+}
@@ -1,6 +1,7 @@
 use glam::Vec2;
 use hecs::World;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 use rapier2d::prelude::*;
 use raylib::prelude::Color;
 
@@ -9,10 +10,18 @@ use crate::{
         Ball, Block, Bouncy, CTransform, InputControlled, OwnedBy, Paddle, Physics, Player, Shape,
         Wall,
     },
-    entity_archetypes::{spawn_ball, spawn_block, spawn_paddle, spawn_walls},
-    level_data,
-    physics_engine::{m2p, p2m, PhysicsEngine},
-    state::{GameMode, GameOverMode, LevelCompleteMode, PrepareLevelMode, State, WinGameMode},
+    entity_archetypes::{spawn_ball, spawn_block, spawn_boss, spawn_paddle, spawn_walls},
+    input_processing::TitleInputs,
+    levels::{
+        get_level, FIRST_PLAYED_ROW, MULTI_HIT_COLOR_INDEX, PLAYED_ROWS, UNBREAKABLE_COLOR_INDEX,
+    },
+    physics_engine::{m2p, p2m},
+    replay,
+    run_stats::RunStats,
+    state::{
+        GameMode, GameOverMode, LevelCompleteMode, PrepareLevelMode, State, WinGameMode,
+        FIRST_EXTRA_LIFE_SCORE, STARTING_LIVES,
+    },
     systems, DIMS, TS_RATIO,
 };
 
@@ -36,6 +45,7 @@ pub fn transition_game_mode(ecs: &mut World, state: &mut State) {
                 win_game_init_state(ecs, state);
             }
             GameMode::GameOver => game_over_init_state(ecs, state),
+            GameMode::Editor => editor_init_state(ecs, state),
         }
         state.game_mode = transition_to;
         state.next_game_mode = None;
@@ -44,15 +54,33 @@ pub fn transition_game_mode(ecs: &mut World, state: &mut State) {
 
 ////////////////////////    PER GAME MODE STATE TRANSITIONS     ////////////////////////
 pub fn title_init_state(ecs: &mut World, state: &mut State) {
+    replay::finish_recording(state);
     ecs.clear();
+    state.physics = state.physics_backend.create();
+}
+
+/// a fresh run from the given level, everything that decides how it plays out comes from the seed
+pub fn start_run(state: &mut State, seed: u64, level: u32) {
+    state.rng_seed = seed;
+    state.rng = ChaCha12Rng::seed_from_u64(seed);
+    state.level = level;
+    state.lives = STARTING_LIVES;
+    state.next_extra_life_score = FIRST_EXTRA_LIFE_SCORE;
+    state.carried_score = 0;
+    state.run_stats = RunStats::new();
+    state.run_frame = 0;
+    state.watchdog_events.clear();
+    state.quit_menu_open = false;
+    state.next_game_mode = Some(GameMode::PrepareLevel);
 }
 
 pub fn prepare_level_init_state(ecs: &mut World, state: &mut State) {
     state.prepare_level_state.mode = PrepareLevelMode::SpawnStuffIn;
     state.prepare_level_state.countdown = (20.0 * TS_RATIO) as u32;
 
+    state.carried_score = systems::playing::scoring::get_total_score(ecs);
     ecs.clear();
-    state.physics = PhysicsEngine::new();
+    state.physics = state.physics_backend.create();
 
     spawn_walls(ecs, state);
 
@@ -73,6 +101,7 @@ pub fn prepare_level_init_state(ecs: &mut World, state: &mut State) {
 
     spawn_level(ecs, state, state.level);
     systems::playing::physics::sync_ecs_to_physics(ecs, state);
+    systems::playing::physics::sync_collider_tags(ecs, state);
     systems::playing::physics::step_physics(ecs, state);
 }
 
@@ -82,9 +111,6 @@ pub fn playing_init_state(ecs: &mut World, state: &mut State) {
 }
 
 pub fn level_complete_init_state(ecs: &mut World, state: &mut State) {
-    if state.level == 2 {
-        state.next_game_mode = Some(GameMode::WinGame);
-    }
     state.level_complete_state.mode = LevelCompleteMode::Announce;
     state.level_complete_state.countdown = (60.0 * TS_RATIO) as u32;
 }
@@ -92,11 +118,27 @@ pub fn level_complete_init_state(ecs: &mut World, state: &mut State) {
 pub fn win_game_init_state(ecs: &mut World, state: &mut State) {
     state.win_game_state.mode = WinGameMode::Announce;
     state.win_game_state.countdown = (60.0 * TS_RATIO) as u32;
+    state.win_game_state.tally_row = 0;
+    state.win_game_state.tally_frame = 0;
+    state.carried_score = systems::playing::scoring::get_total_score(ecs);
+    state.win_game_state.tally_rows = state.run_stats.get_tally_rows(state.carried_score);
+    // a confirm left over from the title would skip the tally
+    state.title_inputs = TitleInputs::new();
 }
 
 pub fn game_over_init_state(ecs: &mut World, state: &mut State) {
     state.game_over_state.mode = GameOverMode::Announce;
     state.game_over_state.countdown = (60.0 * TS_RATIO) as u32;
+    state.carried_score = systems::playing::scoring::get_total_score(ecs);
+}
+
+/// picks the level being edited back up as it was left
+/// escaping to the title and coming back keeps edits that weren't test played or saved
+pub fn editor_init_state(ecs: &mut World, state: &mut State) {
+    ecs.clear();
+    state.physics = state.physics_backend.create();
+    state.editor_state.testing = false;
+    state.editor_state.hovered_cell = None;
 }
 
 pub fn delete_all_blocks(ecs: &mut World, state: &mut State) {
@@ -106,47 +148,46 @@ pub fn delete_all_blocks(ecs: &mut World, state: &mut State) {
         .map(|(entity, _)| entity)
         .collect();
     for block in blocks {
-        // remove all rigid bodies
-        if let Some(rigid_body_handle) = state.physics.get_rigid_body_handle(block) {
-            state.physics.rigid_body_set.remove(
-                rigid_body_handle,
-                &mut state.physics.island_manager,
-                &mut state.physics.collider_set,
-                &mut state.physics.impulse_joint_set,
-                &mut state.physics.multibody_joint_set,
-                true,
-            );
-        }
-
-        // remove from physics
-        // if let Some(collider_handle) = state.physics.get_collider_handle(block) {
-        //     state.physics.collider_set.remove(
-        //         collider_handle,
-        //         &mut state.physics.island_manager,
-        //         &mut state.physics.rigid_body_set,
-        //         true,
-        //     );
-        // }
-
+        state.physics.remove_rigid_body(ecs, block);
         let _ = ecs.despawn(block);
     }
     ecs.flush();
 }
 
+pub const GAP_SIZE: f32 = 1.0;
+pub const BLOCK_WIDTH: f32 = 20.0;
+pub const BLOCK_HEIGHT: f32 = 8.0;
+pub const BLOCK_SHAPE: Vec2 = Vec2::new(BLOCK_WIDTH, BLOCK_HEIGHT);
+/// where the first played row starts, less its gap
+pub const BLOCKS_TOP: f32 = 2.0;
+
+/// the level grid row a block at this height was spawned from
+pub fn get_block_row(block_y: f32) -> usize {
+    let row = ((block_y - BLOCKS_TOP) / (BLOCK_HEIGHT + GAP_SIZE)).max(0.0) as usize;
+    row + FIRST_PLAYED_ROW
+}
+
 pub fn spawn_level(ecs: &mut World, state: &mut State, level: u32) {
     delete_all_blocks(ecs, state);
+    systems::playing::boss::delete_all_bosses(ecs, state);
+
+    // a boss level is just the boss
+    let boss_hp = get_level(&state.levels, level).boss_hp;
+    if boss_hp > 0 {
+        let paddle = ecs
+            .query::<()>()
+            .with::<&Paddle>()
+            .iter()
+            .next()
+            .map(|(entity, _)| entity);
+        spawn_boss(ecs, state, boss_hp, paddle);
+        return;
+    }
 
-    // clamp level between 0 and 35
-    let level = level.clamp(1, 36);
-    let level_index = (level - 1) as usize;
-    let level_data = level_data::LEVEL_BLOCK_DATA[level_index];
-    const GAP_SIZE: f32 = 1.0;
-    const BLOCK_WIDTH: f32 = 20.0;
-    const BLOCK_HEIGHT: f32 = 8.0;
-    const BLOCK_SHAPE: Vec2 = Vec2::new(BLOCK_WIDTH, BLOCK_HEIGHT);
+    let level_data = get_level(&state.levels, level).grid;
     let cursor_x_start = 4.0;
-    let mut cursor = Vec2::new(cursor_x_start, 2.0);
-    for y in 0..=13 {
+    let mut cursor = Vec2::new(cursor_x_start, BLOCKS_TOP);
+    for y in 0..PLAYED_ROWS {
         cursor.x = cursor_x_start;
         // advance cursor y by gap
         cursor.y += GAP_SIZE;
@@ -156,18 +197,29 @@ pub fn spawn_level(ecs: &mut World, state: &mut State, level: u32) {
             if x == 11 {
                 break;
             }
-            let color_index = level_data[y + 2][x];
+            let color_index = level_data[y + FIRST_PLAYED_ROW][x];
             if color_index == 0 {
                 cursor.x += BLOCK_WIDTH;
                 continue;
             }
-            let color = level_data::RL_COLOR_PALETTE[color_index as usize];
 
             // put a block
             // hp is either 1 or 2 if color_index is 9
-            let hp = if color_index == 9 { 2 } else { 1 };
-            let ball_unbreakable = color_index == 10;
-            spawn_block(ecs, state, cursor, BLOCK_SHAPE, color, hp, ball_unbreakable);
+            let hp = if color_index == MULTI_HIT_COLOR_INDEX {
+                2
+            } else {
+                1
+            };
+            let ball_unbreakable = color_index == UNBREAKABLE_COLOR_INDEX;
+            spawn_block(
+                ecs,
+                state,
+                cursor,
+                BLOCK_SHAPE,
+                color_index,
+                hp,
+                ball_unbreakable,
+            );
 
             // advance cursor x by block width
             cursor.x += BLOCK_WIDTH;
@@ -0,0 +1,214 @@
+use glam::Vec2;
+use hecs::World;
+
+use crate::{
+    components::{Ball, Block, CTransform, Health, Paddle, Physics},
+    game_mode_transitions::{start_run, transition_game_mode},
+    input_processing::PlayingInputs,
+    levels::get_levels_hash,
+    replay::{play_replay_frame, start_playback, Replay, ReplayFrame},
+    state::{GameMode, State},
+    step::step,
+    systems::playing::scoring::get_total_score,
+    DIMS,
+};
+
+/// what the player is doing from `frame` until the next scripted input
+pub struct ScriptedInput {
+    pub frame: u32,
+    pub mouse_x: f32,
+    pub confirm: bool,
+    pub restart_level: bool,
+}
+
+/// one input per line as `FRAME MOUSE_X [confirm] [restart]`, in frame order
+/// lines starting with `#` are comments
+///
+/// ```text
+/// 0 120 confirm
+/// 300 60
+/// 420 180 confirm
+/// ```
+pub struct InputScript {
+    pub inputs: Vec<ScriptedInput>,
+}
+
+impl InputScript {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut inputs: Vec<ScriptedInput> = Vec::new();
+        for (line_index, line) in text.lines().enumerate() {
+            let line_number = line_index + 1;
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let frame: u32 = parts
+                .next()
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(|| format!("line {}: expected a frame number", line_number))?;
+            let mouse_x: f32 = parts
+                .next()
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(|| format!("line {}: expected a mouse x", line_number))?;
+            if inputs.last().map_or(false, |last| last.frame > frame) {
+                return Err(format!(
+                    "line {}: frames must not go backwards",
+                    line_number
+                ));
+            }
+            let mut input = ScriptedInput {
+                frame,
+                mouse_x,
+                confirm: false,
+                restart_level: false,
+            };
+            for flag in parts {
+                match flag {
+                    "confirm" => input.confirm = true,
+                    "restart" => input.restart_level = true,
+                    _ => return Err(format!("line {}: unknown input '{}'", line_number, flag)),
+                }
+            }
+            inputs.push(input);
+        }
+        Ok(Self { inputs })
+    }
+
+    /// the input held on this frame, if the script has started
+    pub fn get_input(&self, frame: u32) -> Option<&ScriptedInput> {
+        self.inputs.iter().rev().find(|input| input.frame <= frame)
+    }
+}
+
+/// with no script the paddle chases the lowest ball and holds confirm
+pub fn get_autopilot_input(ecs: &World, frame: u32) -> ScriptedInput {
+    let lowest_ball = ecs
+        .query::<&CTransform>()
+        .with::<&Ball>()
+        .iter()
+        .map(|(_, ctransform)| ctransform.pos)
+        .fold(None, |lowest: Option<Vec2>, pos| match lowest {
+            Some(lowest) if lowest.y >= pos.y => Some(lowest),
+            _ => Some(pos),
+        });
+    ScriptedInput {
+        frame,
+        mouse_x: lowest_ball.map_or(DIMS.x as f32 / 2.0, |pos| pos.x),
+        confirm: true,
+        restart_level: false,
+    }
+}
+
+/// starts the given level and steps it frame by frame without a window
+/// returns a replay of the run, for when a script turns up something worth a bug report
+pub fn run_headless(
+    ecs: &mut World,
+    state: &mut State,
+    level: u32,
+    frames: u32,
+    script: Option<&InputScript>,
+) -> Replay {
+    start_run(state, state.rng_seed, level);
+    let mut recording = Replay::new(
+        state.rng_seed,
+        level,
+        get_levels_hash(&state.levels),
+        state.physics_backend,
+    );
+
+    for frame in 0..frames {
+        transition_game_mode(ecs, state);
+
+        let input = match script {
+            Some(script) => script.get_input(frame).map(|input| ScriptedInput {
+                frame,
+                mouse_x: input.mouse_x,
+                confirm: input.confirm,
+                restart_level: input.restart_level,
+            }),
+            None => Some(get_autopilot_input(ecs, frame)),
+        };
+        let mut playing_inputs = PlayingInputs::new();
+        if let Some(input) = input {
+            state.mouse_screen_pos.x = input.mouse_x;
+            playing_inputs.confirm = input.confirm;
+            playing_inputs.restart_level = input.restart_level;
+        }
+        state.playing_inputs = playing_inputs;
+
+        if let GameMode::Title = state.game_mode {
+            break;
+        }
+        recording.frames.push(ReplayFrame {
+            inputs: state.playing_inputs,
+            mouse_screen_pos: state.mouse_screen_pos,
+        });
+        step_headless(ecs, state);
+        if !state.running {
+            break;
+        }
+    }
+    recording
+}
+
+/// plays a recorded run back until its frames run out
+pub fn run_replay_headless(
+    ecs: &mut World,
+    state: &mut State,
+    replay: Replay,
+) -> Result<(), String> {
+    start_playback(state, replay)?;
+    loop {
+        transition_game_mode(ecs, state);
+        play_replay_frame(state);
+        if state.replay_playback.is_none() {
+            break;
+        }
+        step_headless(ecs, state);
+    }
+    Ok(())
+}
+
+/// mirrors a fixed timestep of the main loop, minus drawing and audio
+pub fn step_headless(ecs: &mut World, state: &mut State) {
+    state.t += 1.0;
+    state.render_command_buffer.clear();
+    state.audio_command_buffer.clear();
+    step(ecs, state);
+    state.center_mouse = false;
+}
+
+/// everything that should match between two runs with the same seed and inputs
+pub fn describe_world(ecs: &World, state: &State) -> String {
+    let mut lines = vec![
+        format!("frame {}", state.t),
+        format!("mode {:?}", state.game_mode),
+        format!("level {}", state.level),
+        format!("lives {}", state.lives),
+        format!("score {}", get_total_score(ecs)),
+        format!(
+            "block hp {}",
+            ecs.query::<&Health>()
+                .with::<&Block>()
+                .iter()
+                .map(|(_, health)| health.hp)
+                .sum::<u32>()
+        ),
+    ];
+    for (entity, ctransform) in ecs.query::<&CTransform>().with::<&Paddle>().iter() {
+        lines.push(format!("paddle {} {:?}", entity.id(), ctransform.pos));
+    }
+    for (entity, (ctransform, physics)) in ecs
+        .query::<(&CTransform, &Physics)>()
+        .with::<&Ball>()
+        .iter()
+    {
+        lines.push(format!(
+            "ball {} {:?} {:?}",
+            entity.id(),
+            ctransform.pos,
+            physics.vel
+        ));
+    }
+    lines.join("\n")
+}
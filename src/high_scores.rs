@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const NAME_LENGTH: usize = 3;
+const HIGH_SCORE_FILE_NAME: &str = "highscores.txt";
+
+#[derive(Clone)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub level: u32,
+    pub date: String,
+}
+
+/// best scores first, stored one per line as `NAME SCORE LEVEL YYYY-MM-DD`
+pub struct HighScoreTable {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScoreTable {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// a missing file is an empty table, unreadable lines are skipped
+    pub fn load() -> Self {
+        let mut table = Self::new();
+        let path = get_high_score_path();
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            return table;
+        };
+        for (line_number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_high_score(line) {
+                Some(high_score) => table.entries.push(high_score),
+                None => println!(
+                    "Skipping bad high score on line {} of {}",
+                    line_number + 1,
+                    path.display()
+                ),
+            }
+        }
+        table.entries.sort_by(|a, b| b.score.cmp(&a.score));
+        table.entries.truncate(MAX_HIGH_SCORES);
+        table
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = get_high_score_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let contents: String = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{} {} {} {}\n",
+                    entry.name, entry.score, entry.level, entry.date
+                )
+            })
+            .collect();
+        std::fs::write(path, contents)
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_HIGH_SCORES
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// ties go below existing entries, returns where it landed
+    pub fn insert(&mut self, high_score: HighScore) -> usize {
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| high_score.score > entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, high_score);
+        self.entries.truncate(MAX_HIGH_SCORES);
+        rank
+    }
+}
+
+fn parse_high_score(line: &str) -> Option<HighScore> {
+    let mut parts = line.split_whitespace();
+    let name = parts.next()?;
+    let score = parts.next()?.parse().ok()?;
+    let level = parts.next()?.parse().ok()?;
+    let date = parts.next()?;
+    if name.len() != NAME_LENGTH || parts.next().is_some() {
+        return None;
+    }
+    Some(HighScore {
+        name: name.to_string(),
+        score,
+        level,
+        date: date.to_string(),
+    })
+}
+
+pub fn get_high_score_path() -> PathBuf {
+    get_data_path(HIGH_SCORE_FILE_NAME)
+}
+
+/// $XDG_DATA_HOME/hecs-arkanoid, falling back to ~/.local/share, then the working directory
+pub fn get_data_path(file_name: &str) -> PathBuf {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    match data_dir {
+        Some(dir) => dir.join("hecs-arkanoid").join(file_name),
+        None => PathBuf::from(file_name),
+    }
+}
+
+/// today's date as YYYY-MM-DD in utc
+pub fn get_date_string() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let days = (seconds / 86_400) as i64;
+
+    // days since 1970-01-01 to a civil date, Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// the three letter arcade name being entered, one letter at a time
+pub struct NameEntryState {
+    pub letters: [u8; NAME_LENGTH],
+    pub cursor: usize,
+}
+
+impl NameEntryState {
+    pub fn new() -> Self {
+        Self {
+            letters: [b'A'; NAME_LENGTH],
+            cursor: 0,
+        }
+    }
+
+    pub fn cycle_letter(&mut self, step: i8) {
+        let letter = &mut self.letters[self.cursor];
+        let index = (*letter - b'A') as i8 + step;
+        *letter = b'A' + index.rem_euclid(26) as u8;
+    }
+
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.letters).to_string()
+    }
+}
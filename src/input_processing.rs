@@ -1,9 +1,26 @@
 use glam::Vec2;
 use raylib::RaylibHandle;
 
-use crate::state::{GameMode, State};
+use crate::{
+    game_mode_transitions::start_run,
+    high_scores::{get_data_path, get_date_string, HighScore, NAME_LENGTH},
+    levels::{get_levels_hash, MULTI_HIT_COLOR_INDEX, UNBREAKABLE_COLOR_INDEX},
+    replay::{start_playback, Replay, REPLAY_FILE_NAME},
+    run_stats::RunStats,
+    state::{GameMode, GameOverMode, State, WinGameMode, FIRST_EXTRA_LIFE_SCORE, STARTING_LIVES},
+    TS_RATIO,
+};
 
 pub fn process_input(rl: &mut RaylibHandle, state: &mut State) {
+    // a replay feeds its own inputs in, escape is the only way out
+    if state.replay_playback.is_some() {
+        if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_ESCAPE) {
+            state.replay_playback = None;
+            state.next_game_mode = Some(GameMode::Title);
+        }
+        return;
+    }
+
     match state.game_mode {
         GameMode::Title => {
             title_process_input(rl, state);
@@ -23,6 +40,9 @@ pub fn process_input(rl: &mut RaylibHandle, state: &mut State) {
         GameMode::GameOver => {
             game_over_process_input(rl, state);
         }
+        GameMode::Editor => {
+            editor_process_input(rl, state);
+        }
     }
 }
 
@@ -36,21 +56,56 @@ pub fn title_process_input(rl: &mut RaylibHandle, state: &mut State) {
     let mouse_pos = Vec2::new(mouse_pos_rl.x, mouse_pos_rl.y);
     state.mouse_screen_pos = mouse_pos;
 
-    let mut title_inputs = TitleInputs { confirm: false };
+    // held until the title step gets to load the save
+    let mut title_inputs = TitleInputs {
+        confirm: false,
+        continue_run: state.title_inputs.continue_run,
+    };
     if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_SPACE) {
         title_inputs.confirm = true;
     }
+    if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_C) && state.has_saved_run {
+        title_inputs.continue_run = true;
+    }
     if title_inputs.confirm {
-        state.next_game_mode = Some(GameMode::PrepareLevel);
+        let seed = rand::random();
+        start_run(state, seed, 1);
+        state.recording = Some(Replay::new(
+            seed,
+            1,
+            get_levels_hash(&state.levels),
+            state.physics_backend,
+        ));
     }
     state.title_inputs = title_inputs;
     state.level = 1;
+    state.lives = STARTING_LIVES;
+    state.next_extra_life_score = FIRST_EXTRA_LIFE_SCORE;
+    state.carried_score = 0;
+    state.run_stats = RunStats::new();
+
+    if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_E) {
+        state.next_game_mode = Some(GameMode::Editor);
+    }
+    if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_R) {
+        let path = get_data_path(REPLAY_FILE_NAME);
+        if let Err(e) = Replay::load(&path).and_then(|replay| start_playback(state, replay)) {
+            println!("Error loading replay {}: {}", path.display(), e);
+        }
+    }
 }
 
 pub fn prepare_level_process_input(rl: &mut RaylibHandle, state: &mut State) {}
 
 pub fn playing_process_input(rl: &mut RaylibHandle, state: &mut State) {
     if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_ESCAPE) {
+        if state.editor_state.testing {
+            state.next_game_mode = Some(GameMode::Editor);
+        } else {
+            state.quit_menu_open = !state.quit_menu_open;
+        }
+    }
+    if state.quit_menu_open && rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_Q) {
         state.running = false;
     }
 
@@ -65,7 +120,13 @@ pub fn playing_process_input(rl: &mut RaylibHandle, state: &mut State) {
         next_level: false,
         previous_level: false,
         restart_level: false,
+        save_and_quit: false,
     };
+    if state.quit_menu_open {
+        inputs.save_and_quit = rl.is_key_down(raylib::consts::KeyboardKey::KEY_ENTER);
+        state.playing_inputs = inputs;
+        return;
+    }
     if rl.is_key_down(raylib::consts::KeyboardKey::KEY_A) {
         inputs.left = true;
     }
@@ -97,12 +158,22 @@ pub fn win_game_process_input(rl: &mut RaylibHandle, state: &mut State) {
         state.running = false;
     }
 
-    let mut title_inputs = TitleInputs { confirm: false };
+    if let WinGameMode::NameEntry = state.win_game_state.mode {
+        if name_entry_process_input(rl, state) {
+            state.win_game_state.mode = WinGameMode::Pause;
+            state.win_game_state.countdown = (300.0 * TS_RATIO) as u32;
+        }
+        return;
+    }
+
+    let mut title_inputs = TitleInputs::new();
     if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_SPACE) {
         title_inputs.confirm = true;
     }
 
-    if title_inputs.confirm {
+    // during the tally confirm skips to the end of it, win_game_step handles that
+    let tallying = matches!(state.win_game_state.mode, WinGameMode::Tally);
+    if title_inputs.confirm && !tallying {
         state.next_game_mode = Some(GameMode::Title);
     }
     state.title_inputs = title_inputs;
@@ -113,21 +184,125 @@ pub fn game_over_process_input(rl: &mut RaylibHandle, state: &mut State) {
         state.running = false;
     }
 
+    if let GameOverMode::NameEntry = state.game_over_state.mode {
+        if name_entry_process_input(rl, state) {
+            state.game_over_state.mode = GameOverMode::Pause;
+            state.game_over_state.countdown = (120.0 * TS_RATIO) as u32;
+        }
+        return;
+    }
+
     if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_SPACE) {
-        state.next_game_mode = Some(GameMode::Title);
+        state.next_game_mode = if state.editor_state.testing {
+            Some(GameMode::Editor)
+        } else {
+            Some(GameMode::Title)
+        };
     }
 }
 
+pub fn editor_process_input(rl: &mut RaylibHandle, state: &mut State) {
+    use raylib::consts::{KeyboardKey, MouseButton};
+    let mouse_pos_rl = rl.get_mouse_position();
+    let mouse_pos = Vec2::new(mouse_pos_rl.x, mouse_pos_rl.y);
+    state.mouse_screen_pos = mouse_pos;
+
+    let mut inputs = EditorInputs::new();
+    inputs.paint = rl.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON);
+    inputs.erase = rl.is_mouse_button_down(MouseButton::MOUSE_RIGHT_BUTTON);
+
+    // number keys pick a colour, silver and gold get their own keys
+    let color_keys = [
+        KeyboardKey::KEY_ONE,
+        KeyboardKey::KEY_TWO,
+        KeyboardKey::KEY_THREE,
+        KeyboardKey::KEY_FOUR,
+        KeyboardKey::KEY_FIVE,
+        KeyboardKey::KEY_SIX,
+        KeyboardKey::KEY_SEVEN,
+        KeyboardKey::KEY_EIGHT,
+    ];
+    for (i, key) in color_keys.iter().enumerate() {
+        if rl.is_key_pressed(*key) {
+            inputs.color_index = Some(i as u32 + 1);
+        }
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_M) {
+        inputs.color_index = Some(MULTI_HIT_COLOR_INDEX);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_U) {
+        inputs.color_index = Some(UNBREAKABLE_COLOR_INDEX);
+    }
+
+    inputs.previous_level = rl.is_key_pressed(KeyboardKey::KEY_LEFT);
+    inputs.next_level = rl.is_key_pressed(KeyboardKey::KEY_RIGHT);
+    inputs.new_level = rl.is_key_pressed(KeyboardKey::KEY_N);
+    inputs.test_play = rl.is_key_pressed(KeyboardKey::KEY_T);
+    inputs.save = rl.is_key_pressed(KeyboardKey::KEY_S);
+    inputs.exit = rl.is_key_pressed(KeyboardKey::KEY_ESCAPE);
+
+    state.editor_inputs = inputs;
+}
+
+/// up and down pick a letter, left and right move between them, space locks one in
+/// returns true once the last letter is locked in and the score is saved
+pub fn name_entry_process_input(rl: &mut RaylibHandle, state: &mut State) -> bool {
+    use raylib::consts::KeyboardKey;
+    let name_entry = &mut state.name_entry;
+    if rl.is_key_pressed(KeyboardKey::KEY_UP) || rl.is_key_pressed(KeyboardKey::KEY_W) {
+        name_entry.cycle_letter(1);
+    }
+    if rl.is_key_pressed(KeyboardKey::KEY_DOWN) || rl.is_key_pressed(KeyboardKey::KEY_S) {
+        name_entry.cycle_letter(-1);
+    }
+    if (rl.is_key_pressed(KeyboardKey::KEY_LEFT)
+        || rl.is_key_pressed(KeyboardKey::KEY_A)
+        || rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE))
+        && name_entry.cursor > 0
+    {
+        name_entry.cursor -= 1;
+    }
+    let confirm =
+        rl.is_key_pressed(KeyboardKey::KEY_SPACE) || rl.is_key_pressed(KeyboardKey::KEY_ENTER);
+    if (rl.is_key_pressed(KeyboardKey::KEY_RIGHT)
+        || rl.is_key_pressed(KeyboardKey::KEY_D)
+        || confirm)
+        && name_entry.cursor < NAME_LENGTH - 1
+    {
+        name_entry.cursor += 1;
+        return false;
+    }
+    if !confirm || name_entry.cursor < NAME_LENGTH - 1 {
+        return false;
+    }
+
+    state.high_scores.insert(HighScore {
+        name: state.name_entry.name(),
+        score: state.carried_score,
+        level: state.level,
+        date: get_date_string(),
+    });
+    if let Err(e) = state.high_scores.save() {
+        println!("Error saving high scores: {}", e);
+    }
+    true
+}
+
 ////////////////////////    INPUT DEFS    ////////////////////////
 pub struct TitleInputs {
     pub confirm: bool,
+    pub continue_run: bool,
 }
 impl TitleInputs {
     pub fn new() -> TitleInputs {
-        TitleInputs { confirm: false }
+        TitleInputs {
+            confirm: false,
+            continue_run: false,
+        }
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
 pub struct PlayingInputs {
     pub left: bool,
     pub right: bool,
@@ -137,6 +312,9 @@ pub struct PlayingInputs {
     pub previous_level: bool,
 
     pub restart_level: bool,
+
+    /// only read while the quit menu is open, never recorded in replays
+    pub save_and_quit: bool,
 }
 impl PlayingInputs {
     pub fn new() -> PlayingInputs {
@@ -149,6 +327,39 @@ impl PlayingInputs {
             previous_level: false,
 
             restart_level: false,
+
+            save_and_quit: false,
+        }
+    }
+}
+
+pub struct EditorInputs {
+    pub paint: bool,
+    pub erase: bool,
+    pub color_index: Option<u32>,
+
+    pub next_level: bool,
+    pub previous_level: bool,
+    pub new_level: bool,
+
+    pub test_play: bool,
+    pub save: bool,
+    pub exit: bool,
+}
+impl EditorInputs {
+    pub fn new() -> EditorInputs {
+        EditorInputs {
+            paint: false,
+            erase: false,
+            color_index: None,
+
+            next_level: false,
+            previous_level: false,
+            new_level: false,
+
+            test_play: false,
+            save: false,
+            exit: false,
         }
     }
 }
@@ -0,0 +1,519 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use raylib::prelude::Color;
+
+use crate::{checksum::get_checksum, level_data};
+
+pub const LEVEL_ROWS: usize = 28;
+pub const LEVEL_COLUMNS: usize = 11;
+/// only these rows of the grid get spawned as blocks
+pub const FIRST_PLAYED_ROW: usize = 2;
+pub const PLAYED_ROWS: usize = 14;
+pub const LEVEL_DIRECTORY: &str = "assets/levels";
+pub const LEVEL_FILE_EXTENSION: &str = "level";
+
+/// silver takes several hits, gold can't be broken by the ball
+pub const MULTI_HIT_COLOR_INDEX: u32 = 9;
+pub const UNBREAKABLE_COLOR_INDEX: u32 = 10;
+
+pub const DEFAULT_PAR_TIME: u32 = 120;
+pub const DEFAULT_BALL_SPEED: f32 = 200.0;
+pub const DEFAULT_MAX_BALL_SPEED: f32 = 320.0;
+pub const DEFAULT_BOSS_HP: u32 = 16;
+
+pub type LevelGrid = [[u32; LEVEL_COLUMNS]; LEVEL_ROWS];
+
+/// one playable level, a grid of palette indices plus how it should be played
+#[derive(Clone)]
+pub struct Level {
+    pub name: String,
+    /// seconds
+    pub par_time: u32,
+    pub background: Color,
+    /// pixels per 60th of a second, before any speed ups
+    pub ball_speed: f32,
+    /// the most the ball can be sped up to
+    pub max_ball_speed: f32,
+    /// a boss level spawns a boss with this much hp instead of its blocks, 0 for a normal level
+    pub boss_hp: u32,
+    pub grid: LevelGrid,
+}
+
+pub struct LevelParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LevelParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+fn error(line: usize, column: usize, message: String) -> LevelParseError {
+    LevelParseError {
+        line,
+        column,
+        message,
+    }
+}
+
+/// the 35 levels compiled into the game and the boss, used when there are no level files
+pub fn get_builtin_levels() -> Vec<Level> {
+    level_data::LEVEL_BLOCK_DATA
+        .iter()
+        .enumerate()
+        .map(|(i, grid)| Level {
+            name: format!("round {}", i + 1),
+            par_time: DEFAULT_PAR_TIME,
+            background: Color::BLACK,
+            ball_speed: DEFAULT_BALL_SPEED,
+            max_ball_speed: DEFAULT_MAX_BALL_SPEED,
+            boss_hp: 0,
+            grid: *grid,
+        })
+        .chain(std::iter::once(get_boss_level()))
+        .collect()
+}
+
+/// the built in final level
+pub fn get_boss_level() -> Level {
+    Level {
+        boss_hp: DEFAULT_BOSS_HP,
+        ..get_empty_level("doh".to_string())
+    }
+}
+
+pub fn get_empty_level(name: String) -> Level {
+    Level {
+        name,
+        par_time: DEFAULT_PAR_TIME,
+        background: Color::BLACK,
+        ball_speed: DEFAULT_BALL_SPEED,
+        max_ball_speed: DEFAULT_MAX_BALL_SPEED,
+        boss_hp: 0,
+        grid: [[0; LEVEL_COLUMNS]; LEVEL_ROWS],
+    }
+}
+
+/// levels are numbered from 1, anything past the end plays the last level
+pub fn get_level(levels: &[Level], level: u32) -> &Level {
+    let index = (level.max(1) as usize - 1).min(levels.len() - 1);
+    &levels[index]
+}
+
+/// beating the last level in the pack wins the game
+pub fn is_final_level(levels: &[Level], level: u32) -> bool {
+    level as usize >= levels.len()
+}
+
+/// every level file in the directory, in file name order
+/// a file that won't load is swapped for the built in level of the same number,
+/// so the levels after it keep their numbers
+/// falls back to the built in levels if none load
+pub fn load_levels(directory: &Path) -> Vec<Level> {
+    let mut paths: Vec<_> = match std::fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |extension| extension == LEVEL_FILE_EXTENSION)
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+
+    let builtin_levels = get_builtin_levels();
+    let mut levels = Vec::new();
+    let mut any_loaded = false;
+    for (index, path) in paths.iter().enumerate() {
+        let loaded = match std::fs::read_to_string(path) {
+            Ok(text) => {
+                parse_level(&text).map_err(|e| format!("Error in level {}:{}", path.display(), e))
+            }
+            Err(e) => Err(format!("Error reading level {}: {}", path.display(), e)),
+        };
+        match loaded {
+            Ok(level) => {
+                levels.push(level);
+                any_loaded = true;
+            }
+            Err(e) => {
+                println!("{}, using the built in level {} instead", e, index + 1);
+                levels.push(match builtin_levels.get(index) {
+                    Some(level) => level.clone(),
+                    None => get_empty_level(format!("round {}", index + 1)),
+                });
+            }
+        }
+    }
+
+    if !any_loaded {
+        println!(
+            "No levels loaded from {}, using the built in levels",
+            directory.display()
+        );
+        return builtin_levels;
+    }
+    levels
+}
+
+/// level files are `key = value` lines followed by a `grid =` line and one row of
+/// palette indices per line, lines starting with `#` are comments
+///
+/// ```text
+/// name = round 1
+/// par_time = 120
+/// background = #000000
+/// ball_speed = 200
+/// max_ball_speed = 320
+/// boss_hp = 16
+/// grid =
+///  0  0  0  0  0  0  0  0  0  0  0
+///  ...28 rows of 11...
+/// ```
+pub fn parse_level(text: &str) -> Result<Level, LevelParseError> {
+    let mut name: Option<String> = None;
+    let mut par_time: Option<u32> = None;
+    let mut background: Option<Color> = None;
+    let mut ball_speed: Option<f32> = None;
+    let mut max_ball_speed: Option<f32> = None;
+    let mut boss_hp: Option<u32> = None;
+    let mut grid: LevelGrid = [[0; LEVEL_COLUMNS]; LEVEL_ROWS];
+    let mut rows_read: Option<usize> = None;
+
+    let mut last_line = 0;
+    for (line_index, raw_line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        last_line = line_number;
+        let line = raw_line;
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        // inside the grid, every line is a row
+        if let Some(row) = rows_read {
+            if row == LEVEL_ROWS {
+                return Err(error(
+                    line_number,
+                    first_column(line),
+                    format!("grid has more than {} rows", LEVEL_ROWS),
+                ));
+            }
+            let mut column = 0;
+            for (token_column, token) in tokens(line) {
+                if column == LEVEL_COLUMNS {
+                    return Err(error(
+                        line_number,
+                        token_column,
+                        format!("row has more than {} columns", LEVEL_COLUMNS),
+                    ));
+                }
+                let color_index: u32 = token.parse().map_err(|_| {
+                    error(
+                        line_number,
+                        token_column,
+                        format!("expected a palette index, found '{}'", token),
+                    )
+                })?;
+                if color_index as usize >= level_data::RL_COLOR_PALETTE.len() {
+                    return Err(error(
+                        line_number,
+                        token_column,
+                        format!(
+                            "palette index {} is out of range 0-{}",
+                            color_index,
+                            level_data::RL_COLOR_PALETTE.len() - 1
+                        ),
+                    ));
+                }
+                grid[row][column] = color_index;
+                column += 1;
+            }
+            if column < LEVEL_COLUMNS {
+                return Err(error(
+                    line_number,
+                    line.trim_end().len() + 1,
+                    format!("row has {} columns, expected {}", column, LEVEL_COLUMNS),
+                ));
+            }
+            rows_read = Some(row + 1);
+            continue;
+        }
+
+        let Some(equals) = line.find('=') else {
+            return Err(error(
+                line_number,
+                first_column(line),
+                "expected 'key = value'".to_string(),
+            ));
+        };
+        let key = line[..equals].trim();
+        let value = line[equals + 1..].trim();
+        let key_column = first_column(line);
+        let value_column =
+            equals + 2 + (line[equals + 1..].len() - line[equals + 1..].trim_start().len());
+
+        match key {
+            "name" => name = Some(value.to_string()),
+            "par_time" => {
+                par_time = Some(value.parse().map_err(|_| {
+                    error(
+                        line_number,
+                        value_column,
+                        format!("expected whole seconds, found '{}'", value),
+                    )
+                })?)
+            }
+            "background" => {
+                background = Some(parse_color(value).ok_or_else(|| {
+                    error(
+                        line_number,
+                        value_column,
+                        format!("expected a colour like #1a1a2e, found '{}'", value),
+                    )
+                })?)
+            }
+            "ball_speed" => ball_speed = Some(parse_speed(value, line_number, value_column)?),
+            "max_ball_speed" => {
+                max_ball_speed = Some(parse_speed(value, line_number, value_column)?)
+            }
+            "boss_hp" => {
+                boss_hp = Some(value.parse().map_err(|_| {
+                    error(
+                        line_number,
+                        value_column,
+                        format!("expected a whole number of hits, found '{}'", value),
+                    )
+                })?)
+            }
+            "grid" => {
+                if !value.is_empty() {
+                    return Err(error(
+                        line_number,
+                        value_column,
+                        "grid rows start on the next line".to_string(),
+                    ));
+                }
+                rows_read = Some(0);
+            }
+            _ => {
+                return Err(error(
+                    line_number,
+                    key_column,
+                    format!("unknown key '{}'", key),
+                ))
+            }
+        }
+    }
+
+    match rows_read {
+        None => Err(error(last_line + 1, 1, "missing 'grid ='".to_string())),
+        Some(rows) if rows < LEVEL_ROWS => Err(error(
+            last_line + 1,
+            1,
+            format!("grid has {} rows, expected {}", rows, LEVEL_ROWS),
+        )),
+        Some(_) => {
+            let ball_speed = ball_speed.unwrap_or(DEFAULT_BALL_SPEED);
+            Ok(Level {
+                name: name.unwrap_or_else(|| "untitled".to_string()),
+                par_time: par_time.unwrap_or(DEFAULT_PAR_TIME),
+                background: background.unwrap_or(Color::BLACK),
+                ball_speed,
+                // a level can't start the ball faster than it is allowed to go
+                max_ball_speed: max_ball_speed
+                    .unwrap_or(DEFAULT_MAX_BALL_SPEED)
+                    .max(ball_speed),
+                boss_hp: boss_hp.unwrap_or(0),
+                grid,
+            })
+        }
+    }
+}
+
+/// writes a level back out in the same format parse_level reads
+pub fn level_to_string(level: &Level) -> String {
+    let mut text = String::new();
+    text.push_str(&format!("name = {}\n", level.name));
+    text.push_str(&format!("par_time = {}\n", level.par_time));
+    text.push_str(&format!(
+        "background = #{:02x}{:02x}{:02x}\n",
+        level.background.r, level.background.g, level.background.b
+    ));
+    text.push_str(&format!("ball_speed = {}\n", level.ball_speed));
+    text.push_str(&format!("max_ball_speed = {}\n", level.max_ball_speed));
+    if level.boss_hp > 0 {
+        text.push_str(&format!("boss_hp = {}\n", level.boss_hp));
+    }
+    text.push_str("grid =\n");
+    for row in level.grid.iter() {
+        let row: Vec<String> = row.iter().map(|index| format!("{:2}", index)).collect();
+        text.push_str(&row.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+/// tells level packs apart, a replay only plays back on the levels it was recorded with
+pub fn get_levels_hash(levels: &[Level]) -> u64 {
+    let text: String = levels.iter().map(level_to_string).collect();
+    get_checksum(&text)
+}
+
+/// writes the level to `NN.level` in the directory, so it loads back in as level NN
+pub fn save_level(directory: &Path, level_number: u32, level: &Level) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(directory)?;
+    let path = directory.join(format!("{:02}.{}", level_number, LEVEL_FILE_EXTENSION));
+    std::fs::write(&path, level_to_string(level))?;
+    Ok(path)
+}
+
+fn parse_speed(value: &str, line: usize, column: usize) -> Result<f32, LevelParseError> {
+    value
+        .parse()
+        .ok()
+        .filter(|speed: &f32| *speed > 0.0)
+        .ok_or_else(|| {
+            error(
+                line,
+                column,
+                format!("expected a positive speed, found '{}'", value),
+            )
+        })
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some(Color::new(
+        (rgb >> 16) as u8,
+        (rgb >> 8) as u8,
+        rgb as u8,
+        255,
+    ))
+}
+
+/// whitespace separated tokens with their 1 based columns
+fn tokens(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.split_whitespace().map(move |token| {
+        let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+        (offset + 1, token)
+    })
+}
+
+fn first_column(line: &str) -> usize {
+    line.len() - line.trim_start().len() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a level file with the given rows at the top of the grid and empty rows after
+    fn level_text(rows: &[&str]) -> String {
+        let mut text = "name = test\npar_time = 90\nball_speed = 150\ngrid =\n".to_string();
+        for row in rows {
+            text.push_str(row);
+            text.push('\n');
+        }
+        for _ in rows.len()..LEVEL_ROWS {
+            text.push_str(" 0  0  0  0  0  0  0  0  0  0  0\n");
+        }
+        text
+    }
+
+    fn parse_error(text: &str) -> LevelParseError {
+        match parse_level(text) {
+            Ok(_) => panic!("expected the level not to parse"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parses_a_valid_level() {
+        let text = level_text(&[" 1  2  3  4  5  6  7  8  9 10  0"]);
+        let Ok(level) = parse_level(&text) else {
+            panic!("expected the level to parse");
+        };
+        assert_eq!(level.name, "test");
+        assert_eq!(level.par_time, 90);
+        assert_eq!(level.ball_speed, 150.0);
+        assert_eq!(level.max_ball_speed, DEFAULT_MAX_BALL_SPEED);
+        assert_eq!(level.boss_hp, 0);
+        assert_eq!(level.grid[0], [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 0]);
+        assert_eq!(level.grid[LEVEL_ROWS - 1], [0; LEVEL_COLUMNS]);
+    }
+
+    #[test]
+    fn round_trips_through_level_to_string() {
+        let level = &get_builtin_levels()[0];
+        let Ok(parsed) = parse_level(&level_to_string(level)) else {
+            panic!("expected the written level to parse");
+        };
+        assert_eq!(parsed.name, level.name);
+        assert_eq!(parsed.grid, level.grid);
+    }
+
+    #[test]
+    fn reports_a_palette_index_out_of_range() {
+        let text = level_text(&[" 1  2  3 11  0  0  0  0  0  0  0"]);
+        let e = parse_error(&text);
+        assert_eq!((e.line, e.column), (5, 10));
+        assert!(e.message.contains("palette index 11"), "{}", e.message);
+    }
+
+    #[test]
+    fn reports_a_short_row_at_its_end() {
+        let text = level_text(&[" 1  1  1  1  1  1  1  1  1  1"]);
+        let e = parse_error(&text);
+        assert_eq!((e.line, e.column), (5, 30));
+        assert_eq!(e.message, "row has 10 columns, expected 11");
+    }
+
+    #[test]
+    fn reports_a_long_row_at_the_extra_column() {
+        let text = level_text(&[" 0  0  0  0  0  0  0  0  0  0  0  1"]);
+        let e = parse_error(&text);
+        assert_eq!((e.line, e.column), (5, 35));
+        assert_eq!(e.message, "row has more than 11 columns");
+    }
+
+    #[test]
+    fn broken_level_file_keeps_its_number() {
+        let directory = std::env::temp_dir().join(format!("levels_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("01.level"), level_text(&[])).unwrap();
+        std::fs::write(directory.join("02.level"), "grid = 1\n").unwrap();
+        std::fs::write(
+            directory.join("03.level"),
+            level_text(&[]).replace("name = test", "name = third"),
+        )
+        .unwrap();
+
+        let levels = load_levels(&directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(levels.len(), 3);
+        assert_eq!(levels[1].grid, get_builtin_levels()[1].grid);
+        assert_eq!(levels[2].name, "third");
+    }
+
+    #[test]
+    fn displays_line_and_column() {
+        let e = parse_error("name = test\nspeed = 3\n");
+        assert_eq!(e.to_string(), "2:1: unknown key 'speed'");
+    }
+
+    #[test]
+    fn reports_missing_rows_after_the_last_line() {
+        let e = parse_error("name = test\ngrid =\n 0  0  0  0  0  0  0  0  0  0  0\n");
+        assert_eq!((e.line, e.column), (4, 1));
+        assert_eq!(e.message, "grid has 1 rows, expected 28");
+    }
+}
@@ -0,0 +1,38 @@
+use glam::UVec2;
+use lazy_static::lazy_static;
+
+pub mod aabb_physics;
+pub mod audio;
+pub mod audio_playing;
+pub mod checksum;
+pub mod collision;
+pub mod components;
+pub mod entity_archetypes;
+pub mod game_mode_transitions;
+pub mod headless;
+pub mod high_scores;
+pub mod input_processing;
+pub mod level_data;
+pub mod levels;
+pub mod message_stream;
+pub mod physics_backend;
+pub mod physics_engine;
+pub mod physics_hooks;
+pub mod render;
+pub mod render_commands;
+pub mod replay;
+pub mod run_stats;
+pub mod save_game;
+pub mod state;
+pub mod step;
+pub mod systems;
+pub mod timer;
+pub mod window_helpers;
+
+pub const DIMS: UVec2 = UVec2::new(240, 160);
+lazy_static! {
+    pub static ref WINDOW_DIMS: UVec2 = DIMS * 4;
+}
+
+pub const TIMESTEP: f32 = 1.0 / state::FRAMES_PER_SECOND as f32;
+pub const TS_RATIO: f32 = state::FRAMES_PER_SECOND as f32 / 60.0;
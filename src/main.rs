@@ -1,38 +1,22 @@
-use audio::Song;
-use audio_playing::execute_audio_command_buffer;
-use glam::{UVec2, Vec2};
+use glam::Vec2;
 use hecs::World;
 use raylib::prelude::*;
 use raylib::{ffi::SetTraceLogLevel, prelude::TraceLogLevel};
-use window_helpers::{center_window, scale_and_blit_render_texture_to_window};
-
-mod audio;
-mod audio_playing;
-mod components;
-mod entity_archetypes;
-mod game_mode_transitions;
-mod input_processing;
-mod level_data;
-mod message_stream;
-mod physics_engine;
-mod render;
-mod render_commands;
-mod state;
-mod step;
-mod systems;
-mod timer;
-mod window_helpers;
-
-pub const DIMS: UVec2 = UVec2::new(240, 160);
-use lazy_static::lazy_static;
-lazy_static! {
-    pub static ref WINDOW_DIMS: UVec2 = DIMS * 4;
-}
 
-const TIMESTEP: f32 = 1.0 / state::FRAMES_PER_SECOND as f32;
-const TS_RATIO: f32 = state::FRAMES_PER_SECOND as f32 / 60.0;
+use hecs_arkanoid::{
+    audio::{self, Song},
+    audio_playing::execute_audio_command_buffer,
+    game_mode_transitions, input_processing,
+    physics_backend::PhysicsBackendKind,
+    render, replay, state, step,
+    window_helpers::{center_window, scale_and_blit_render_texture_to_window},
+    DIMS, TIMESTEP, WINDOW_DIMS,
+};
+
 fn main() {
     let (mut rl, rlt) = raylib::init().title("raylib-rs-lowres-template").build();
+    // escape opens the quit menu and backs out of the editor, each mode decides when it quits
+    rl.set_exit_key(None);
     unsafe {
         SetTraceLogLevel(TraceLogLevel::LOG_WARNING as i32);
     }
@@ -84,6 +68,35 @@ fn main() {
     let mut state = state::State::new();
     let mut ecs = World::new();
 
+    let args: Vec<String> = std::env::args().collect();
+
+    // `--physics rapier|aabb` picks the physics engine, rapier unless told otherwise
+    if let Some(i) = args.iter().position(|arg| arg == "--physics") {
+        let Some(physics_backend) = args
+            .get(i + 1)
+            .and_then(|name| PhysicsBackendKind::from_name(name))
+        else {
+            println!("--physics needs rapier or aabb");
+            std::process::exit(1);
+        };
+        state.set_physics_backend(physics_backend);
+    }
+
+    // `--replay FILE` plays a recorded run back instead of starting at the title
+    // on the physics it was recorded with, whatever --physics says
+    if let Some(i) = args.iter().position(|arg| arg == "--replay") {
+        let Some(path) = args.get(i + 1) else {
+            println!("--replay needs a replay file");
+            std::process::exit(1);
+        };
+        let loaded = replay::Replay::load(std::path::Path::new(path))
+            .and_then(|loaded| replay::start_playback(&mut state, loaded));
+        if let Err(e) = loaded {
+            println!("Error loading replay {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+
     ////////////////    MAIN LOOP    ////////////////
     let mut fps_history = std::collections::VecDeque::with_capacity(10);
     while state.running && !rl.window_should_close() {
@@ -121,7 +134,14 @@ fn main() {
             state.render_command_buffer.clear();
             state.audio_command_buffer.clear();
 
-            step::step(&mut rl, &mut ecs, &mut state);
+            replay::play_replay_frame(&mut state);
+            replay::record_replay_frame(&mut state);
+            step::step(&mut ecs, &mut state);
+            if state.center_mouse {
+                let center = (*WINDOW_DIMS).as_vec2();
+                rl.set_mouse_position(Vector2::new(center.x / 2.0, center.y));
+                state.center_mouse = false;
+            }
             ////////////////    AUDIO STEP  ////////////////
             execute_audio_command_buffer(&mut rl, &mut audio, &mut state.audio_command_buffer);
         }
@@ -156,4 +176,7 @@ fn main() {
         fps_history.push_back(fps);
         state.fps = fps_history.iter().sum::<f32>() / fps_history.len() as f32;
     }
+
+    // quitting mid run still keeps the replay
+    replay::finish_recording(&mut state);
 }
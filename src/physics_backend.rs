@@ -0,0 +1,243 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+
+use crate::{
+    aabb_physics::AabbPhysics, components::HasRigidBody, physics_engine::PhysicsEngine,
+    physics_hooks::ColliderTag,
+};
+
+/// seconds per physics step, rapier's default, speeds are scaled for it by TS_RATIO
+pub const PHYSICS_DT: f32 = 1.0 / 60.0;
+
+/// which physics runs the game, picked once at startup
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PhysicsBackendKind {
+    #[default]
+    Rapier,
+    SweptAabb,
+}
+
+impl PhysicsBackendKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rapier" => Some(PhysicsBackendKind::Rapier),
+            "aabb" => Some(PhysicsBackendKind::SweptAabb),
+            _ => None,
+        }
+    }
+
+    /// how replays store it
+    pub fn to_byte(self) -> u8 {
+        match self {
+            PhysicsBackendKind::Rapier => 0,
+            PhysicsBackendKind::SweptAabb => 1,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(PhysicsBackendKind::Rapier),
+            1 => Some(PhysicsBackendKind::SweptAabb),
+            _ => None,
+        }
+    }
+
+    /// an empty world for this backend
+    pub fn create(self) -> Box<dyn PhysicsBackend> {
+        match self {
+            PhysicsBackendKind::Rapier => Box::new(PhysicsEngine::new()),
+            PhysicsBackendKind::SweptAabb => Box::new(AabbPhysics::new()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyKind {
+    /// never moves
+    Fixed,
+    /// moved by its velocity and bounced off whatever it hits
+    Dynamic,
+    /// moved by setting its center, pushes dynamic bodies out of the way
+    KinematicPosition,
+    /// moved by its velocity, but nothing pushes it back
+    KinematicVelocity,
+}
+
+/// the channels a body is on, and the channels it collides with
+/// two bodies only touch when each is on a channel the other collides with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionGroups {
+    pub memberships: u32,
+    pub filter: u32,
+}
+
+impl CollisionGroups {
+    pub const ALL: CollisionGroups = CollisionGroups {
+        memberships: u32::MAX,
+        filter: u32::MAX,
+    };
+
+    pub fn new(memberships: u32, filter: u32) -> Self {
+        Self {
+            memberships,
+            filter,
+        }
+    }
+
+    pub fn interacts_with(self, other: CollisionGroups) -> bool {
+        self.memberships & other.filter != 0 && other.memberships & self.filter != 0
+    }
+}
+
+/// a body with one box collider, in pixels and pixels per second
+#[derive(Clone, Copy, Debug)]
+pub struct BodyDesc {
+    pub kind: BodyKind,
+    pub center: Vec2,
+    pub dims: Vec2,
+    pub vel: Vec2,
+    pub groups: CollisionGroups,
+    /// overlaps are reported but nothing bounces
+    pub sensor: bool,
+    /// contacts involving this body are reported as ContactEvents
+    pub events: bool,
+    /// sweeps the body along its path so it can't skip through thin things
+    pub ccd: bool,
+    /// also touches other kinematic bodies
+    pub kinematic_contacts: bool,
+}
+
+impl BodyDesc {
+    pub fn new(kind: BodyKind, center: Vec2, dims: Vec2) -> Self {
+        Self {
+            kind,
+            center,
+            dims,
+            vel: Vec2::ZERO,
+            groups: CollisionGroups::ALL,
+            sensor: false,
+            events: false,
+            ccd: false,
+            kinematic_contacts: false,
+        }
+    }
+
+    pub fn vel(mut self, vel: Vec2) -> Self {
+        self.vel = vel;
+        self
+    }
+
+    pub fn groups(mut self, memberships: u32, filter: u32) -> Self {
+        self.groups = CollisionGroups::new(memberships, filter);
+        self
+    }
+
+    pub fn sensor(mut self) -> Self {
+        self.sensor = true;
+        self
+    }
+
+    pub fn events(mut self) -> Self {
+        self.events = true;
+        self
+    }
+
+    pub fn ccd(mut self) -> Self {
+        self.ccd = true;
+        self
+    }
+
+    pub fn kinematic_contacts(mut self) -> Self {
+        self.kinematic_contacts = true;
+        self
+    }
+}
+
+/// a body in whichever backend is running, a stale handle just finds nothing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BodyHandle {
+    pub index: u32,
+    pub generation: u32,
+}
+
+/// two bodies started or stopped touching during the last step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContactEvent {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    pub started: bool,
+}
+
+/// what playing_step needs from a physics engine, everything in pixels
+/// bodies are axis aligned boxes that never turn
+/// every body belongs to exactly one entity: the backend remembers each body's entity,
+/// and the entity's HasRigidBody holds the body's handle
+/// bodies only come and go through insert_rigid_body and remove_rigid_body, which keep both sides
+pub trait PhysicsBackend {
+    fn add_body(&mut self, entity: Entity, desc: BodyDesc) -> BodyHandle;
+    fn delete_body(&mut self, body: BodyHandle);
+    fn get_entity(&self, body: BodyHandle) -> Option<Entity>;
+    fn get_bodies(&self) -> Vec<BodyHandle>;
+
+    fn get_center(&self, body: BodyHandle) -> Option<Vec2>;
+    fn get_dims(&self, body: BodyHandle) -> Option<Vec2>;
+    fn get_vel(&self, body: BodyHandle) -> Option<Vec2>;
+
+    fn set_center(&mut self, body: BodyHandle, center: Vec2);
+    fn set_dims(&mut self, body: BodyHandle, dims: Vec2);
+    fn set_vel(&mut self, body: BodyHandle, vel: Vec2);
+    fn set_kind(&mut self, body: BodyHandle, kind: BodyKind);
+    fn set_groups(&mut self, body: BodyHandle, groups: CollisionGroups);
+    fn set_ccd(&mut self, body: BodyHandle, enabled: bool);
+    /// what the gameplay rules need to know about the body during the step
+    fn set_tag(&mut self, body: BodyHandle, tag: ColliderTag);
+
+    /// advances one timestep
+    fn step(&mut self);
+    /// everything that started or stopped touching during the last step
+    fn get_contact_events(&self) -> &[ContactEvent];
+
+    /// adds the body for the entity, and gives the entity a HasRigidBody
+    fn insert_rigid_body(&mut self, ecs: &mut World, entity: Entity, desc: BodyDesc) -> BodyHandle {
+        let handle = self.add_body(entity, desc);
+        let _ = ecs.insert_one(entity, HasRigidBody { handle });
+        handle
+    }
+
+    /// takes away the entity's body, the entity itself is left alone
+    fn remove_rigid_body(&mut self, ecs: &mut World, entity: Entity) {
+        if let Ok(has_rigid_body) = ecs.remove_one::<HasRigidBody>(entity) {
+            self.delete_body(has_rigid_body.handle);
+        }
+    }
+
+    /// panics if a body and its entity have lost track of each other, only in debug builds
+    fn debug_check_entity_mapping(&self, ecs: &World) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        for (entity, has_rigid_body) in ecs.query::<&HasRigidBody>().iter() {
+            debug_assert_eq!(
+                self.get_entity(has_rigid_body.handle),
+                Some(entity),
+                "{:?} points at a body that isn't its own",
+                entity
+            );
+        }
+        for body in self.get_bodies() {
+            let entity = self.get_entity(body);
+            let handle = entity.and_then(|entity| {
+                ecs.get::<&HasRigidBody>(entity)
+                    .ok()
+                    .map(|has_rigid_body| has_rigid_body.handle)
+            });
+            debug_assert_eq!(
+                handle,
+                Some(body),
+                "body {:?} belongs to {:?}, which doesn't know about it",
+                body,
+                entity
+            );
+        }
+    }
+}
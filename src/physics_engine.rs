@@ -1,14 +1,19 @@
+use glam::Vec2;
 use rapier2d::{
     crossbeam::{self, channel::Receiver},
-    na::{OPoint, Vector2},
+    na::Vector2,
     prelude::*,
 };
 
-use crate::DIMS;
+use crate::{
+    physics_backend::{
+        BodyDesc, BodyHandle, BodyKind, CollisionGroups, ContactEvent, PhysicsBackend,
+    },
+    physics_hooks::{ColliderTag, GameplayHooks},
+};
 
 use hecs::Entity;
 use rapier2d::dynamics::RigidBodyHandle;
-use std::collections::HashMap;
 
 const PIXELS_PER_METER: f32 = 120.0;
 const METERS_PER_PIXEL: f32 = 1.0 / PIXELS_PER_METER;
@@ -21,10 +26,16 @@ pub fn p2m(p: f32) -> f32 {
     p * METERS_PER_PIXEL
 }
 
-pub struct PhysicsEngine {
-    ecs_to_rigid_body: HashMap<Entity, RigidBodyHandle>,
-    rigid_body_to_ecs: HashMap<RigidBodyHandle, Entity>,
+fn p2m_vec(p: Vec2) -> Vector2<f32> {
+    Vector2::new(p2m(p.x), p2m(p.y))
+}
+
+fn m2p_vec(m: &Vector2<f32>) -> Vec2 {
+    Vec2::new(m2p(m.x), m2p(m.y))
+}
 
+/// the rapier backend, a body's user_data holds its entity's bits
+pub struct PhysicsEngine {
     pub gravity: Vector2<f32>,
     pub integration_parameters: IntegrationParameters,
     pub physics_pipeline: PhysicsPipeline,
@@ -34,12 +45,12 @@ pub struct PhysicsEngine {
     pub impulse_joint_set: ImpulseJointSet,
     pub multibody_joint_set: MultibodyJointSet,
     pub ccd_solver: CCDSolver,
-    pub physics_hooks: (),
+    pub physics_hooks: GameplayHooks,
     pub collision_recv: Receiver<CollisionEvent>,
     pub contact_force_recv: Receiver<ContactForceEvent>,
     pub event_handler: ChannelEventCollector,
 
-    pub collision_events: Vec<CollisionEvent>,
+    pub contact_events: Vec<ContactEvent>,
 
     pub rigid_body_set: RigidBodySet,
     pub collider_set: ColliderSet,
@@ -65,9 +76,6 @@ impl PhysicsEngine {
         let collider_set = ColliderSet::new();
 
         Self {
-            ecs_to_rigid_body: HashMap::new(),
-            rigid_body_to_ecs: HashMap::new(),
-
             gravity,
             integration_parameters,
             physics_pipeline,
@@ -77,7 +85,7 @@ impl PhysicsEngine {
             impulse_joint_set,
             multibody_joint_set,
             ccd_solver,
-            physics_hooks: (),
+            physics_hooks: GameplayHooks,
             collision_recv,
             contact_force_recv,
             event_handler,
@@ -85,11 +93,190 @@ impl PhysicsEngine {
             rigid_body_set,
             collider_set,
 
-            collision_events: Vec::new(),
+            contact_events: Vec::new(),
+        }
+    }
+
+    /// the entity whose body the collider is attached to
+    pub fn get_entity_from_collider_handle(
+        &self,
+        collider_handle: ColliderHandle,
+    ) -> Option<Entity> {
+        self.collider_set
+            .get(collider_handle)
+            .and_then(|collider| collider.parent())
+            .and_then(|physics_handle| self.get_entity(to_body_handle(physics_handle)))
+    }
+
+    /// every collider on the body, there is only ever the one
+    fn for_each_collider(&mut self, body: BodyHandle, mut f: impl FnMut(&mut Collider)) {
+        let Some(rigid_body) = self.rigid_body_set.get(to_rigid_body_handle(body)) else {
+            return;
+        };
+        for collider_handle in rigid_body.colliders().iter() {
+            if let Some(collider) = self.collider_set.get_mut(*collider_handle) {
+                f(collider);
+            }
         }
     }
+}
 
-    pub fn step(&mut self) {
+fn to_rigid_body_handle(body: BodyHandle) -> RigidBodyHandle {
+    RigidBodyHandle::from_raw_parts(body.index, body.generation)
+}
+
+fn to_body_handle(physics_handle: RigidBodyHandle) -> BodyHandle {
+    let (index, generation) = physics_handle.into_raw_parts();
+    BodyHandle { index, generation }
+}
+
+fn to_rigid_body_type(kind: BodyKind) -> RigidBodyType {
+    match kind {
+        BodyKind::Fixed => RigidBodyType::Fixed,
+        BodyKind::Dynamic => RigidBodyType::Dynamic,
+        BodyKind::KinematicPosition => RigidBodyType::KinematicPositionBased,
+        BodyKind::KinematicVelocity => RigidBodyType::KinematicVelocityBased,
+    }
+}
+
+fn to_interaction_groups(groups: CollisionGroups) -> InteractionGroups {
+    InteractionGroups::new(
+        Group::from_bits_truncate(groups.memberships),
+        Group::from_bits_truncate(groups.filter),
+    )
+}
+
+impl PhysicsBackend for PhysicsEngine {
+    fn add_body(&mut self, entity: Entity, desc: BodyDesc) -> BodyHandle {
+        let mut collider = ColliderBuilder::cuboid(p2m(desc.dims.x) / 2.0, p2m(desc.dims.y) / 2.0)
+            .sensor(desc.sensor)
+            .collision_groups(to_interaction_groups(desc.groups));
+        if !desc.sensor {
+            collider = collider.restitution(1.0).friction(0.0);
+        }
+        // the ball is the only solid thing that moves, it mustn't shove anything around
+        // and only its contacts need the gameplay hooks
+        if !desc.sensor && desc.kind == BodyKind::Dynamic {
+            collider = collider.mass(0.0001).active_hooks(
+                ActiveHooks::FILTER_CONTACT_PAIRS | ActiveHooks::MODIFY_SOLVER_CONTACTS,
+            );
+        }
+        if desc.events {
+            collider = collider.active_events(ActiveEvents::COLLISION_EVENTS);
+        }
+        if desc.kinematic_contacts {
+            collider = collider.active_collision_types(
+                ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_KINEMATIC,
+            );
+        }
+
+        let mut rigid_body = RigidBodyBuilder::new(to_rigid_body_type(desc.kind))
+            .translation(p2m_vec(desc.center))
+            .linvel(p2m_vec(desc.vel))
+            .can_sleep(false)
+            .ccd_enabled(desc.ccd);
+        if desc.kind != BodyKind::Fixed {
+            rigid_body = rigid_body.lock_rotations();
+        }
+        let mut rigid_body = rigid_body.build();
+        rigid_body.user_data = entity.to_bits().get() as u128;
+
+        let physics_handle = self.rigid_body_set.insert(rigid_body);
+        self.collider_set.insert_with_parent(
+            collider.build(),
+            physics_handle,
+            &mut self.rigid_body_set,
+        );
+        to_body_handle(physics_handle)
+    }
+
+    fn delete_body(&mut self, body: BodyHandle) {
+        self.rigid_body_set.remove(
+            to_rigid_body_handle(body),
+            &mut self.island_manager,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            true, // remove the associated colliders as well
+        );
+    }
+
+    fn get_entity(&self, body: BodyHandle) -> Option<Entity> {
+        self.rigid_body_set
+            .get(to_rigid_body_handle(body))
+            .and_then(|rigid_body| Entity::from_bits(rigid_body.user_data as u64))
+    }
+
+    fn get_bodies(&self) -> Vec<BodyHandle> {
+        self.rigid_body_set
+            .iter()
+            .map(|(physics_handle, _)| to_body_handle(physics_handle))
+            .collect()
+    }
+
+    fn get_center(&self, body: BodyHandle) -> Option<Vec2> {
+        self.rigid_body_set
+            .get(to_rigid_body_handle(body))
+            .map(|rigid_body| m2p_vec(&rigid_body.position().translation.vector))
+    }
+
+    fn get_dims(&self, body: BodyHandle) -> Option<Vec2> {
+        let rigid_body = self.rigid_body_set.get(to_rigid_body_handle(body))?;
+        let collider = self.collider_set.get(*rigid_body.colliders().first()?)?;
+        let cuboid = collider.shape().as_cuboid()?;
+        Some(m2p_vec(&cuboid.half_extents) * 2.0)
+    }
+
+    fn get_vel(&self, body: BodyHandle) -> Option<Vec2> {
+        self.rigid_body_set
+            .get(to_rigid_body_handle(body))
+            .map(|rigid_body| m2p_vec(rigid_body.linvel()))
+    }
+
+    fn set_center(&mut self, body: BodyHandle, center: Vec2) {
+        if let Some(rigid_body) = self.rigid_body_set.get_mut(to_rigid_body_handle(body)) {
+            rigid_body.set_position(p2m_vec(center).into(), true);
+        }
+    }
+
+    fn set_dims(&mut self, body: BodyHandle, dims: Vec2) {
+        self.for_each_collider(body, |collider| {
+            collider.set_shape(ColliderShape::cuboid(p2m(dims.x / 2.0), p2m(dims.y / 2.0)));
+        });
+    }
+
+    fn set_vel(&mut self, body: BodyHandle, vel: Vec2) {
+        if let Some(rigid_body) = self.rigid_body_set.get_mut(to_rigid_body_handle(body)) {
+            rigid_body.set_linvel(p2m_vec(vel), true);
+        }
+    }
+
+    fn set_kind(&mut self, body: BodyHandle, kind: BodyKind) {
+        if let Some(rigid_body) = self.rigid_body_set.get_mut(to_rigid_body_handle(body)) {
+            rigid_body.set_body_type(to_rigid_body_type(kind), true);
+        }
+    }
+
+    fn set_groups(&mut self, body: BodyHandle, groups: CollisionGroups) {
+        self.for_each_collider(body, |collider| {
+            collider.set_collision_groups(to_interaction_groups(groups));
+        });
+    }
+
+    fn set_ccd(&mut self, body: BodyHandle, enabled: bool) {
+        if let Some(rigid_body) = self.rigid_body_set.get_mut(to_rigid_body_handle(body)) {
+            rigid_body.enable_ccd(enabled);
+        }
+    }
+
+    /// read back by GameplayHooks from the collider's user_data
+    fn set_tag(&mut self, body: BodyHandle, tag: ColliderTag) {
+        self.for_each_collider(body, |collider| {
+            collider.user_data = tag.to_user_data();
+        });
+    }
+
+    fn step(&mut self) {
         self.physics_pipeline.step(
             &self.gravity,
             &self.integration_parameters,
@@ -105,28 +292,23 @@ impl PhysicsEngine {
             &self.physics_hooks,
             &self.event_handler,
         );
-    }
-
-    pub fn set_rigid_body_mapping(&mut self, ecs_entity: Entity, physics_handle: RigidBodyHandle) {
-        self.ecs_to_rigid_body.insert(ecs_entity, physics_handle);
-        self.rigid_body_to_ecs.insert(physics_handle, ecs_entity);
-    }
 
-    pub fn remove_rigid_body_mapping(&mut self, ecs_entity: Entity) {
-        if let Some(physics_handle) = self.ecs_to_rigid_body.remove(&ecs_entity) {
-            self.rigid_body_to_ecs.remove(&physics_handle);
+        // colliders removed since the last step have no entity left, so their events are dropped
+        self.contact_events.clear();
+        while let Ok(event) = self.collision_recv.try_recv() {
+            let entity_a = self.get_entity_from_collider_handle(event.collider1());
+            let entity_b = self.get_entity_from_collider_handle(event.collider2());
+            if let (Some(entity_a), Some(entity_b)) = (entity_a, entity_b) {
+                self.contact_events.push(ContactEvent {
+                    entity_a,
+                    entity_b,
+                    started: event.started(),
+                });
+            }
         }
-        self.ecs_to_rigid_body.remove(&ecs_entity);
     }
 
-    pub fn get_rigid_body_handle(&self, ecs_entity: Entity) -> Option<RigidBodyHandle> {
-        self.ecs_to_rigid_body.get(&ecs_entity).copied()
-    }
-
-    pub fn get_entity_from_rigid_body_handle(
-        &self,
-        physics_handle: RigidBodyHandle,
-    ) -> Option<Entity> {
-        self.rigid_body_to_ecs.get(&physics_handle).copied()
+    fn get_contact_events(&self) -> &[ContactEvent] {
+        &self.contact_events
     }
 }
@@ -0,0 +1,134 @@
+use glam::Vec2;
+use rapier2d::prelude::*;
+
+use crate::systems::playing::physics::get_paddle_bounce_direction;
+
+/// what the hooks know about a collider, packed into its user_data
+/// synced from the entity's components every step, see sync_collider_tags
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ColliderTag {
+    pub flags: u32,
+    /// a paddle's english, in radians of tilt
+    pub english: f32,
+}
+
+impl ColliderTag {
+    pub const BALL: u32 = 1 << 0;
+    pub const PIERCING_BALL: u32 = 1 << 1;
+    pub const BREAKABLE_BLOCK: u32 = 1 << 2;
+    pub const PADDLE: u32 = 1 << 3;
+
+    pub fn has(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    pub fn to_user_data(self) -> u128 {
+        self.flags as u128 | (self.english.to_bits() as u128) << 32
+    }
+
+    pub fn from_user_data(user_data: u128) -> Self {
+        Self {
+            flags: user_data as u32,
+            english: f32::from_bits((user_data >> 32) as u32),
+        }
+    }
+
+    /// balls pass through each other
+    pub fn ghosts(&self, other: ColliderTag) -> bool {
+        self.has(ColliderTag::BALL) && other.has(ColliderTag::BALL)
+    }
+
+    /// a piercing ball touches breakable blocks without bouncing off them, either way round
+    pub fn pierces(&self, other: ColliderTag) -> bool {
+        let pierces = |ball: &ColliderTag, block: &ColliderTag| {
+            ball.has(ColliderTag::PIERCING_BALL) && block.has(ColliderTag::BREAKABLE_BLOCK)
+        };
+        pierces(self, &other) || pierces(&other, self)
+    }
+}
+
+fn get_tag(colliders: &ColliderSet, collider: ColliderHandle) -> ColliderTag {
+    colliders
+        .get(collider)
+        .map_or(ColliderTag::default(), |collider| {
+            ColliderTag::from_user_data(collider.user_data)
+        })
+}
+
+/// decides collision outcomes inside the physics step
+/// rapier only asks about pairs where one of the colliders has the matching active hooks
+pub struct GameplayHooks;
+
+impl PhysicsHooks for GameplayHooks {
+    fn filter_contact_pair(&self, context: &PairFilterContext) -> Option<SolverFlags> {
+        let tag1 = get_tag(context.colliders, context.collider1);
+        let tag2 = get_tag(context.colliders, context.collider2);
+        if tag1.ghosts(tag2) {
+            return None;
+        }
+        // the contact is kept so the block still hears about it and breaks,
+        // but there is no impulse to bounce the ball off it
+        if tag1.pierces(tag2) {
+            return Some(SolverFlags::empty());
+        }
+        Some(SolverFlags::COMPUTE_IMPULSES)
+    }
+
+    fn modify_solver_contacts(&self, context: &mut ContactModificationContext) {
+        let tag1 = get_tag(context.colliders, context.collider1);
+        let tag2 = get_tag(context.colliders, context.collider2);
+        if tag1.has(ColliderTag::BALL) && tag2.has(ColliderTag::PADDLE) {
+            bounce_off_paddle(context, context.collider1, context.collider2, tag2, 1.0);
+        } else if tag2.has(ColliderTag::BALL) && tag1.has(ColliderTag::PADDLE) {
+            bounce_off_paddle(context, context.collider2, context.collider1, tag1, -1.0);
+        }
+    }
+}
+
+/// turns the contact normal halfway between where the ball is going and where it should go,
+/// so a perfectly elastic bounce sends it off at exactly the paddle's angle
+/// the normal points from collider1 to collider2, normal_sign is -1 when the ball is collider2
+fn bounce_off_paddle(
+    context: &mut ContactModificationContext,
+    ball: ColliderHandle,
+    paddle: ColliderHandle,
+    paddle_tag: ColliderTag,
+    normal_sign: f32,
+) {
+    let (Some(ball_collider), Some(paddle_collider)) =
+        (context.colliders.get(ball), context.colliders.get(paddle))
+    else {
+        return;
+    };
+    let Some(ball_body) = ball_collider
+        .parent()
+        .and_then(|body| context.bodies.get(body))
+    else {
+        return;
+    };
+    let Some(half_width) = paddle_collider
+        .shape()
+        .as_cuboid()
+        .map(|cuboid| cuboid.half_extents.x)
+    else {
+        return;
+    };
+
+    let vel = Vec2::new(ball_body.linvel().x, ball_body.linvel().y);
+    let into_paddle = Vec2::new(context.normal.x, context.normal.y) * normal_sign;
+    // already on its way out, leave it be
+    if vel.dot(into_paddle) <= 0.0 {
+        return;
+    }
+
+    let offset = (ball_collider.translation().x - paddle_collider.translation().x) / half_width;
+    let direction = get_paddle_bounce_direction(offset, paddle_tag.english, vel);
+    let Some(normal) = (vel - direction * vel.length()).try_normalize() else {
+        return;
+    };
+    *context.normal = vector![normal.x, normal.y] * normal_sign;
+    for solver_contact in context.solver_contacts.iter_mut() {
+        solver_contact.restitution = 1.0;
+        solver_contact.friction = 0.0;
+    }
+}
@@ -2,9 +2,17 @@ use glam::Vec2;
 use raylib::prelude::{Color, RaylibDraw, RaylibDrawHandle, RaylibTextureMode};
 
 use crate::{
+    level_data::RL_COLOR_PALETTE,
+    levels::{
+        get_level, FIRST_PLAYED_ROW, MULTI_HIT_COLOR_INDEX, PLAYED_ROWS, UNBREAKABLE_COLOR_INDEX,
+    },
     render_commands::execute_render_command_buffer,
     state::{GameMode, GameOverMode, LevelCompleteMode, PrepareLevelMode, State, WinGameMode},
-    DIMS,
+    step::{
+        EDITOR_CELL_GAP, EDITOR_CELL_SHAPE, EDITOR_GRID_ORIGIN, EDITOR_PALETTE_ORIGIN,
+        EDITOR_SWATCH_SIZE, EDITOR_SWATCH_SPACING, TALLY_FRAMES_PER_ROW,
+    },
+    DIMS, TS_RATIO,
 };
 
 pub fn draw(state: &State, low_res_draw_handle: &mut RaylibTextureMode<RaylibDrawHandle>) {
@@ -27,11 +35,20 @@ pub fn draw(state: &State, low_res_draw_handle: &mut RaylibTextureMode<RaylibDra
         GameMode::GameOver => {
             game_over_render(state, low_res_draw_handle);
         }
+        GameMode::Editor => {
+            editor_render(state, low_res_draw_handle);
+        }
+    }
+
+    if let Some(playback) = &state.replay_playback {
+        let text = format!("replay {}/{}", playback.frame, playback.replay.frames.len());
+        low_res_draw_handle.draw_text(text.as_str(), 4, 4, 1, Color::YELLOW);
     }
 }
 
 ////////////////////////    PER GAME MODE DRAW FUNCTIONS     ////////////////////////
-pub fn title_render(_state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
+const HIGH_SCORE_CYCLE_FRAMES: f32 = 180.0 * TS_RATIO;
+pub fn title_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
     let mut cursor = Vec2::new(DIMS.x as f32 * 0.15, DIMS.y as f32 * 0.4);
     let title = "HECS-arkanoid!";
     let size = 20;
@@ -47,6 +64,96 @@ pub fn title_render(_state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>)
         size,
         Color::WHITE,
     );
+    cursor.y += 12.0;
+    if state.has_saved_run {
+        d.draw_text(
+            "press c to continue",
+            cursor.x as i32,
+            cursor.y as i32,
+            size,
+            Color::WHITE,
+        );
+        cursor.y += 12.0;
+    }
+    d.draw_text(
+        "press e to edit levels",
+        cursor.x as i32,
+        cursor.y as i32,
+        size,
+        Color::GRAY,
+    );
+    cursor.y += 12.0;
+    d.draw_text(
+        "press r to watch the last run",
+        cursor.x as i32,
+        cursor.y as i32,
+        size,
+        Color::GRAY,
+    );
+    cursor.y += 20.0;
+
+    // cycle through the high score table one entry at a time
+    let entries = &state.high_scores.entries;
+    if !entries.is_empty() {
+        let index = (state.t / HIGH_SCORE_CYCLE_FRAMES) as usize % entries.len();
+        let entry = &entries[index];
+        let text = format!(
+            "{}. {} {} L{} {}",
+            index + 1,
+            entry.name,
+            entry.score,
+            entry.level,
+            entry.date
+        );
+        d.draw_text(
+            text.as_str(),
+            cursor.x as i32,
+            cursor.y as i32,
+            size,
+            Color::GRAY,
+        );
+    }
+}
+
+/// the arcade style name entry, shared by game over and win game
+pub fn name_entry_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
+    let mut cursor = Vec2::new(DIMS.x as f32 * 0.15, DIMS.y as f32 * 0.3);
+    let size = 1;
+    d.draw_text(
+        format!("new high score {}", state.carried_score).as_str(),
+        cursor.x as i32,
+        cursor.y as i32,
+        size,
+        Color::RAYWHITE,
+    );
+    cursor.y += 20.0;
+
+    let letter_size = 20;
+    let letter_spacing = 20.0;
+    for (i, letter) in state.name_entry.letters.iter().enumerate() {
+        let x = cursor.x + i as f32 * letter_spacing;
+        let color = if i == state.name_entry.cursor {
+            Color::YELLOW
+        } else {
+            Color::RAYWHITE
+        };
+        d.draw_text(
+            (*letter as char).to_string().as_str(),
+            x as i32,
+            cursor.y as i32,
+            letter_size,
+            color,
+        );
+        if i == state.name_entry.cursor {
+            d.draw_rectangle(
+                x as i32,
+                (cursor.y + letter_size as f32 + 2.0) as i32,
+                12,
+                2,
+                Color::YELLOW,
+            );
+        }
+    }
 }
 
 pub fn prepare_level_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
@@ -93,11 +200,39 @@ pub fn prepare_level_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawH
             Color::WHITE,
         );
         cursor.y += size as f32 * 1.5;
+
+        let level = get_level(&state.levels, state.level);
+        let subtitle = format!("{}  par {}s", level.name, level.par_time);
+        d.draw_text(
+            subtitle.as_str(),
+            cursor.x as i32,
+            cursor.y as i32,
+            1,
+            Color::WHITE,
+        );
     }
 }
 
 pub fn playing_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
     execute_render_command_buffer(d, &state.render_command_buffer);
+
+    if state.quit_menu_open {
+        d.draw_rectangle(0, 0, DIMS.x as i32, DIMS.y as i32, Color::new(0, 0, 0, 160));
+        let mut cursor = Vec2::new(DIMS.x as f32 * 0.15, DIMS.y as f32 * 0.3);
+        let size = 20;
+        d.draw_text(
+            "paused",
+            cursor.x as i32,
+            cursor.y as i32,
+            size,
+            Color::RAYWHITE,
+        );
+        cursor.y += size as f32 * 1.5;
+        for line in ["enter  save and quit", "q  quit", "esc  keep playing"] {
+            d.draw_text(line, cursor.x as i32, cursor.y as i32, 1, Color::RAYWHITE);
+            cursor.y += 12.0;
+        }
+    }
 }
 
 const MESSAGES_OF_ENCOURAGEMENT: [&str; 35] = [
@@ -141,7 +276,8 @@ const MESSAGES_OF_ENCOURAGEMENT: [&str; 35] = [
 pub fn level_complete_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
     if let LevelCompleteMode::Announce = state.level_complete_state.mode {
         let mut cursor = Vec2::new(DIMS.x as f32 * 0.15, DIMS.y as f32 * 0.4);
-        let title = MESSAGES_OF_ENCOURAGEMENT[state.level as usize - 1];
+        let title =
+            MESSAGES_OF_ENCOURAGEMENT[(state.level as usize - 1) % MESSAGES_OF_ENCOURAGEMENT.len()];
         let size = 20;
         d.draw_text(
             title,
@@ -195,6 +331,57 @@ pub fn win_game_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle
             Color::RAYWHITE,
         );
         cursor.y += size as f32 * 1.5;
+    } else if let WinGameMode::NameEntry = state.win_game_state.mode {
+        name_entry_render(state, d);
+    } else {
+        win_game_tally_render(state, d);
+    }
+}
+
+const TALLY_VISIBLE_ROWS: usize = 10;
+/// the stats table, rows appear one at a time with the newest one counting up
+pub fn win_game_tally_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
+    let rows = &state.win_game_state.tally_rows;
+    let (shown, counting) = match state.win_game_state.mode {
+        WinGameMode::Tally => (state.win_game_state.tally_row + 1, true),
+        _ => (rows.len(), false),
+    };
+    let shown = shown.min(rows.len());
+    let first = shown.saturating_sub(TALLY_VISIBLE_ROWS);
+
+    let size = 1;
+    let mut cursor = Vec2::new(DIMS.x as f32 * 0.1, DIMS.y as f32 * 0.05);
+    d.draw_text(
+        "results",
+        cursor.x as i32,
+        cursor.y as i32,
+        size,
+        Color::RAYWHITE,
+    );
+    cursor.y += 14.0;
+
+    for (i, row) in rows.iter().enumerate().take(shown).skip(first) {
+        let value = if counting && i == shown - 1 {
+            let progress = state.win_game_state.tally_frame as f32 / TALLY_FRAMES_PER_ROW as f32;
+            (row.value as f32 * progress) as u32
+        } else {
+            row.value
+        };
+        d.draw_text(
+            row.label.as_str(),
+            cursor.x as i32,
+            cursor.y as i32,
+            size,
+            Color::RAYWHITE,
+        );
+        d.draw_text(
+            value.to_string().as_str(),
+            (DIMS.x as f32 * 0.7) as i32,
+            cursor.y as i32,
+            size,
+            Color::RAYWHITE,
+        );
+        cursor.y += 12.0;
     }
 }
 
@@ -223,5 +410,128 @@ pub fn game_over_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandl
             Color::RAYWHITE,
         );
         cursor.y += size as f32 * 1.5;
+    } else if let GameOverMode::NameEntry = state.game_over_state.mode {
+        name_entry_render(state, d);
+    }
+}
+
+pub fn editor_render(state: &State, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
+    let editor_state = &state.editor_state;
+    d.clear_background(editor_state.level.background);
+
+    // the grid, rows that never get spawned are shaded
+    let pitch = EDITOR_CELL_SHAPE + EDITOR_CELL_GAP;
+    for (row, colors) in editor_state.level.grid.iter().enumerate() {
+        let played = (FIRST_PLAYED_ROW..FIRST_PLAYED_ROW + PLAYED_ROWS).contains(&row);
+        for (column, color_index) in colors.iter().enumerate() {
+            let pos = EDITOR_GRID_ORIGIN + Vec2::new(column as f32, row as f32) * pitch;
+            let (x, y) = (pos.x as i32, pos.y as i32);
+            let (w, h) = (EDITOR_CELL_SHAPE.x as i32, EDITOR_CELL_SHAPE.y as i32);
+            if *color_index == 0 {
+                let color = if played {
+                    Color::DARKGRAY
+                } else {
+                    Color::new(40, 40, 40, 255)
+                };
+                d.draw_rectangle_lines(x, y, w, h, color);
+                continue;
+            }
+            d.draw_rectangle(x, y, w, h, RL_COLOR_PALETTE[*color_index as usize]);
+            if *color_index == MULTI_HIT_COLOR_INDEX {
+                d.draw_rectangle_lines(x, y, w, h, Color::WHITE);
+            } else if *color_index == UNBREAKABLE_COLOR_INDEX {
+                d.draw_rectangle_lines(x, y, w, h, Color::YELLOW);
+            }
+            if !played {
+                d.draw_rectangle(x, y, w, h, Color::new(0, 0, 0, 160));
+            }
+        }
     }
+    if let Some((row, column)) = editor_state.hovered_cell {
+        let pos = EDITOR_GRID_ORIGIN + Vec2::new(column as f32, row as f32) * pitch;
+        d.draw_rectangle_lines(
+            pos.x as i32 - 1,
+            pos.y as i32 - 1,
+            EDITOR_CELL_SHAPE.x as i32 + 2,
+            EDITOR_CELL_SHAPE.y as i32 + 2,
+            Color::RAYWHITE,
+        );
+    }
+
+    // the side panel
+    let size = 1;
+    let mut cursor = Vec2::new(EDITOR_PALETTE_ORIGIN.x, EDITOR_GRID_ORIGIN.y);
+    let title = format!("{} {}", editor_state.level_number, editor_state.level.name);
+    d.draw_text(
+        title.as_str(),
+        cursor.x as i32,
+        cursor.y as i32,
+        size,
+        Color::WHITE,
+    );
+
+    for color_index in 1..RL_COLOR_PALETTE.len() {
+        let x = EDITOR_PALETTE_ORIGIN.x + (color_index - 1) as f32 * EDITOR_SWATCH_SPACING;
+        let y = EDITOR_PALETTE_ORIGIN.y;
+        let swatch_size = EDITOR_SWATCH_SIZE as i32;
+        d.draw_rectangle(
+            x as i32,
+            y as i32,
+            swatch_size,
+            swatch_size,
+            RL_COLOR_PALETTE[color_index],
+        );
+        if color_index as u32 == editor_state.color_index {
+            d.draw_rectangle_lines(
+                x as i32 - 1,
+                y as i32 - 1,
+                swatch_size + 2,
+                swatch_size + 2,
+                Color::RAYWHITE,
+            );
+        }
+    }
+
+    cursor.y = EDITOR_PALETTE_ORIGIN.y + EDITOR_SWATCH_SIZE + 6.0;
+    let help = [
+        "1-8 colour",
+        "m silver  u gold",
+        "lmb paint  rmb erase",
+        "<- -> level  n new",
+        "t test  s save",
+        "esc title",
+    ];
+    for line in help.iter() {
+        d.draw_text(line, cursor.x as i32, cursor.y as i32, size, Color::GRAY);
+        cursor.y += 12.0;
+    }
+
+    cursor.y += 6.0;
+    for message in state.expiring_messages.iter() {
+        d.draw_text(
+            message.text.as_str(),
+            cursor.x as i32,
+            cursor.y as i32,
+            size,
+            Color::YELLOW,
+        );
+        cursor.y += 12.0;
+    }
+
+    // the real cursor is hidden, so draw one
+    let mouse = state.mouse_screen_pos;
+    d.draw_line(
+        mouse.x as i32 - 2,
+        mouse.y as i32,
+        mouse.x as i32 + 3,
+        mouse.y as i32,
+        Color::WHITE,
+    );
+    d.draw_line(
+        mouse.x as i32,
+        mouse.y as i32 - 2,
+        mouse.x as i32,
+        mouse.y as i32 + 3,
+        Color::WHITE,
+    );
 }
@@ -25,6 +25,11 @@ pub enum RenderCommand {
         dims: Vec2,
         color: Color,
     },
+    PowerUp {
+        pos: Vec2,
+        dims: Vec2,
+        color: Color,
+    },
     Text {
         pos: Vec2,
         text: String,
@@ -46,6 +51,26 @@ pub enum RenderCommand {
         dims: Vec2,
         color: Color,
     },
+    /// a big face with an eye that follows the paddle, white while flashing from a hit
+    Boss {
+        pos: Vec2,
+        dims: Vec2,
+        facing: Vec2,
+        flashing: bool,
+    },
+    /// a ragged ring, for explosions
+    Blast {
+        pos: Vec2,
+        radius: f32,
+        color: Color,
+    },
+    /// an outline filled from the left by fraction
+    HpBar {
+        pos: Vec2,
+        dims: Vec2,
+        fraction: f32,
+        color: Color,
+    },
 }
 
 // defualt entity size
@@ -111,6 +136,22 @@ pub fn execute_render_command_buffer(
                 dims.y as i32,
                 color,
             ),
+            RenderCommand::PowerUp { pos, dims, color } => {
+                d.draw_rectangle(
+                    pos.x as i32,
+                    pos.y as i32,
+                    dims.x as i32,
+                    dims.y as i32,
+                    *color,
+                );
+                d.draw_rectangle_lines(
+                    pos.x as i32,
+                    pos.y as i32,
+                    dims.x as i32,
+                    dims.y as i32,
+                    Color::RAYWHITE,
+                );
+            }
             RenderCommand::Text {
                 pos,
                 text,
@@ -138,6 +179,76 @@ pub fn execute_render_command_buffer(
                     *color,
                 );
             }
+            RenderCommand::Boss {
+                pos,
+                dims,
+                facing,
+                flashing,
+            } => {
+                let color = if *flashing {
+                    Color::RAYWHITE
+                } else {
+                    Color::ORANGE
+                };
+                d.draw_rectangle_lines(
+                    pos.x as i32,
+                    pos.y as i32,
+                    dims.x as i32,
+                    dims.y as i32,
+                    color,
+                );
+                // the eye sits in the upper half, its pupil pushed towards the paddle
+                let eye = Vector2::new(pos.x + dims.x / 2.0, pos.y + dims.y / 3.0);
+                let eye_radius = dims.x / 6.0;
+                d.draw_circle_lines(eye.x as i32, eye.y as i32, eye_radius, color);
+                d.draw_circle_v(
+                    Vector2::new(
+                        eye.x + facing.x * eye_radius / 2.0,
+                        eye.y + facing.y * eye_radius / 2.0,
+                    ),
+                    eye_radius / 3.0,
+                    color,
+                );
+                // and a mouth
+                d.draw_rectangle(
+                    (pos.x + dims.x / 4.0) as i32,
+                    (pos.y + dims.y * 0.7) as i32,
+                    (dims.x / 2.0) as i32,
+                    2,
+                    color,
+                );
+            }
+            RenderCommand::Blast { pos, radius, color } => {
+                let point = |i: usize| {
+                    let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+                    let r = radius * RADIUS_VARIATIONS[i % SEGMENTS];
+                    Vector2::new(pos.x + angle.cos() * r, pos.y + angle.sin() * r)
+                };
+                for i in 0..SEGMENTS {
+                    d.draw_line_v(point(i), point(i + 1), *color);
+                }
+            }
+            RenderCommand::HpBar {
+                pos,
+                dims,
+                fraction,
+                color,
+            } => {
+                d.draw_rectangle(
+                    pos.x as i32,
+                    pos.y as i32,
+                    (dims.x * fraction.clamp(0.0, 1.0)) as i32,
+                    dims.y as i32,
+                    *color,
+                );
+                d.draw_rectangle_lines(
+                    pos.x as i32,
+                    pos.y as i32,
+                    dims.x as i32,
+                    dims.y as i32,
+                    Color::RAYWHITE,
+                );
+            }
         }
     }
 }
@@ -0,0 +1,255 @@
+use std::path::Path;
+
+use glam::Vec2;
+
+use crate::{
+    game_mode_transitions::start_run,
+    input_processing::PlayingInputs,
+    levels::get_levels_hash,
+    physics_backend::PhysicsBackendKind,
+    state::{GameMode, State},
+};
+
+pub const REPLAY_FILE_NAME: &str = "last.replay";
+const REPLAY_MAGIC: &[u8; 4] = b"ARKR";
+const REPLAY_VERSION: u8 = 3;
+/// count, mouse x, mouse y, input bits
+const RUN_SIZE: usize = 4 + 4 + 4 + 1;
+
+/// what went into one fixed timestep
+#[derive(Clone, Copy, PartialEq)]
+pub struct ReplayFrame {
+    pub inputs: PlayingInputs,
+    pub mouse_screen_pos: Vec2,
+}
+
+/// everything needed to play a run back exactly, the same seed and level plus every frame's input
+pub struct Replay {
+    pub seed: u64,
+    pub level: u32,
+    /// get_levels_hash of the levels the run was played on
+    pub levels_hash: u64,
+    /// the two backends bounce differently, a run only plays back on the one it was played on
+    pub physics_backend: PhysicsBackendKind,
+    pub frames: Vec<ReplayFrame>,
+}
+
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub frame: usize,
+}
+
+impl Replay {
+    pub fn new(
+        seed: u64,
+        level: u32,
+        levels_hash: u64,
+        physics_backend: PhysicsBackendKind,
+    ) -> Self {
+        Self {
+            seed,
+            level,
+            levels_hash,
+            physics_backend,
+            frames: Vec::new(),
+        }
+    }
+
+    /// a header then runs of identical frames, the mouse sits still for most of a run
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut runs: Vec<(u32, ReplayFrame)> = Vec::new();
+        for frame in self.frames.iter() {
+            match runs.last_mut() {
+                Some((count, last)) if last == frame => *count += 1,
+                _ => runs.push((1, *frame)),
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(30 + runs.len() * RUN_SIZE);
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.level.to_le_bytes());
+        bytes.extend_from_slice(&self.levels_hash.to_le_bytes());
+        bytes.push(self.physics_backend.to_byte());
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (count, frame) in runs {
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes.extend_from_slice(&frame.mouse_screen_pos.x.to_le_bytes());
+            bytes.extend_from_slice(&frame.mouse_screen_pos.y.to_le_bytes());
+            bytes.push(inputs_to_bits(&frame.inputs));
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = ByteReader { bytes, offset: 0 };
+        if reader.take(4)? != REPLAY_MAGIC {
+            return Err("not a replay file".to_string());
+        }
+        let version = reader.take(1)?[0];
+        if version != REPLAY_VERSION {
+            return Err(format!("unsupported replay version {}", version));
+        }
+        let seed = reader.read_u64()?;
+        let level = reader.read_u32()?;
+        let levels_hash = reader.read_u64()?;
+        let physics_byte = reader.take(1)?[0];
+        let physics_backend = PhysicsBackendKind::from_byte(physics_byte)
+            .ok_or_else(|| format!("unknown physics {}", physics_byte))?;
+        let num_runs = reader.read_u32()?;
+
+        let mut replay = Self::new(seed, level, levels_hash, physics_backend);
+        for _ in 0..num_runs {
+            let count = reader.read_u32()?;
+            let x = f32::from_bits(reader.read_u32()?);
+            let y = f32::from_bits(reader.read_u32()?);
+            let inputs = bits_to_inputs(reader.take(1)?[0]);
+            let frame = ReplayFrame {
+                inputs,
+                mouse_screen_pos: Vec2::new(x, y),
+            };
+            replay
+                .frames
+                .extend(std::iter::repeat(frame).take(count as usize));
+        }
+        if reader.offset != bytes.len() {
+            return Err(format!(
+                "{} unexpected bytes after the last frame",
+                bytes.len() - reader.offset
+            ));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.offset + len;
+        if end > self.bytes.len() {
+            return Err(format!("replay ends early at byte {}", self.bytes.len()));
+        }
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+fn inputs_to_bits(inputs: &PlayingInputs) -> u8 {
+    [
+        inputs.left,
+        inputs.right,
+        inputs.confirm,
+        inputs.next_level,
+        inputs.previous_level,
+        inputs.restart_level,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (i, held)| bits | ((*held as u8) << i))
+}
+
+fn bits_to_inputs(bits: u8) -> PlayingInputs {
+    let held = |i: u8| bits & (1 << i) != 0;
+    PlayingInputs {
+        left: held(0),
+        right: held(1),
+        confirm: held(2),
+        next_level: held(3),
+        previous_level: held(4),
+        restart_level: held(5),
+        save_and_quit: false,
+    }
+}
+
+/// call right before each step, so the frames line up with the steps they fed
+/// title frames are left out, a run starts on its first PrepareLevel step
+/// so are frames paused on the quit menu, they don't step the game
+pub fn record_replay_frame(state: &mut State) {
+    if let GameMode::Title = state.game_mode {
+        return;
+    }
+    if state.quit_menu_open {
+        return;
+    }
+    if let Some(recording) = state.recording.as_mut() {
+        recording.frames.push(ReplayFrame {
+            inputs: state.playing_inputs,
+            mouse_screen_pos: state.mouse_screen_pos,
+        });
+    }
+}
+
+/// saves the run that just ended as the last replay
+pub fn finish_recording(state: &mut State) {
+    let Some(recording) = state.recording.take() else {
+        return;
+    };
+    let path = crate::high_scores::get_data_path(REPLAY_FILE_NAME);
+    if let Err(e) = recording.save(&path) {
+        println!("Error saving replay {}: {}", path.display(), e);
+    }
+}
+
+/// a replay recorded on other levels would play out differently, so it isn't started
+/// the game switches to the physics the replay was recorded with, and keeps it afterwards
+pub fn start_playback(state: &mut State, replay: Replay) -> Result<(), String> {
+    if replay.levels_hash != get_levels_hash(&state.levels) {
+        return Err("recorded with different levels".to_string());
+    }
+    state.set_physics_backend(replay.physics_backend);
+    state.recording = None;
+    start_run(state, replay.seed, replay.level);
+    state.replay_playback = Some(ReplayPlayback { replay, frame: 0 });
+    Ok(())
+}
+
+/// call right before each step in place of reading inputs, ends the playback when it runs out
+/// a run that makes it back to the title is over, even with frames left
+pub fn play_replay_frame(state: &mut State) {
+    let Some(playback) = state.replay_playback.as_mut() else {
+        return;
+    };
+    if let GameMode::Title = state.game_mode {
+        if playback.frame > 0 {
+            state.replay_playback = None;
+        }
+        return;
+    }
+    match playback.replay.frames.get(playback.frame) {
+        Some(frame) => {
+            state.playing_inputs = frame.inputs;
+            state.mouse_screen_pos = frame.mouse_screen_pos;
+            playback.frame += 1;
+        }
+        None => {
+            state.replay_playback = None;
+            state.next_game_mode = Some(GameMode::Title);
+        }
+    }
+}
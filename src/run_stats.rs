@@ -0,0 +1,93 @@
+use crate::state::FRAMES_PER_SECOND;
+
+#[derive(Clone)]
+pub struct LevelStats {
+    pub level: u32,
+    pub blocks_broken: u32,
+    pub frames: u32,
+}
+
+/// everything worth bragging about over the course of a run
+#[derive(Clone)]
+pub struct RunStats {
+    pub levels: Vec<LevelStats>,
+    pub balls_lost: u32,
+    pub power_ups_collected: u32,
+    pub longest_combo: u32,
+}
+
+pub struct TallyRow {
+    pub label: String,
+    pub value: u32,
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        Self {
+            levels: Vec::new(),
+            balls_lost: 0,
+            power_ups_collected: 0,
+            longest_combo: 0,
+        }
+    }
+
+    /// stats for the level being played, replaying a level after losing a life adds to it
+    pub fn level_mut(&mut self, level: u32) -> &mut LevelStats {
+        if self.levels.last().map(|stats| stats.level) != Some(level) {
+            self.levels.push(LevelStats {
+                level,
+                blocks_broken: 0,
+                frames: 0,
+            });
+        }
+        self.levels.last_mut().unwrap()
+    }
+
+    pub fn total_blocks_broken(&self) -> u32 {
+        self.levels.iter().map(|stats| stats.blocks_broken).sum()
+    }
+
+    pub fn total_seconds(&self) -> u32 {
+        self.levels.iter().map(|stats| stats.frames).sum::<u32>() / FRAMES_PER_SECOND
+    }
+
+    /// the rows counted up one by one on the win screen, score last
+    pub fn get_tally_rows(&self, score: u32) -> Vec<TallyRow> {
+        let mut rows = Vec::new();
+        for stats in self.levels.iter() {
+            rows.push(TallyRow {
+                label: format!("level {} blocks", stats.level),
+                value: stats.blocks_broken,
+            });
+            rows.push(TallyRow {
+                label: format!("level {} seconds", stats.level),
+                value: stats.frames / FRAMES_PER_SECOND,
+            });
+        }
+        rows.push(TallyRow {
+            label: "blocks broken".to_string(),
+            value: self.total_blocks_broken(),
+        });
+        rows.push(TallyRow {
+            label: "total seconds".to_string(),
+            value: self.total_seconds(),
+        });
+        rows.push(TallyRow {
+            label: "balls lost".to_string(),
+            value: self.balls_lost,
+        });
+        rows.push(TallyRow {
+            label: "power ups".to_string(),
+            value: self.power_ups_collected,
+        });
+        rows.push(TallyRow {
+            label: "longest combo".to_string(),
+            value: self.longest_combo,
+        });
+        rows.push(TallyRow {
+            label: "score".to_string(),
+            value: score,
+        });
+        rows
+    }
+}
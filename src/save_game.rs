@@ -0,0 +1,672 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use raylib::prelude::Color;
+use strum::IntoEnumIterator;
+
+use crate::{
+    components::{
+        ActivePowerUp, ActivePowerUps, AttachedTo, Ball, BallSpeed, Block, Boss, BossProjectile,
+        Bullet, CTransform, Combo, Enemy, EnemyGate, Explosion, Health, Paddle, Physics, PowerUp,
+        PowerUpType, Score, Shape, StrongBlock, WantsToGoTo,
+    },
+    entity_archetypes::{
+        spawn_ball, spawn_block, spawn_boss, spawn_boss_projectile, spawn_bullet, spawn_enemy,
+        spawn_explosion, spawn_paddle, spawn_powerup, spawn_walls, BALL_SHAPE,
+    },
+    game_mode_transitions::BASE_PADDLE_SHAPE,
+    high_scores::get_data_path,
+    run_stats::{LevelStats, RunStats},
+    state::{GameMode, State},
+    systems::playing::attachment::attach,
+    timer::{BossFireTimer, EnemySpawnTimer, LaserFireTimer},
+};
+
+pub const SAVE_FILE_NAME: &str = "save.txt";
+const SAVE_HEADER: &str = "hecs-arkanoid save 3";
+
+pub struct SavedBlock {
+    pub pos: Vec2,
+    pub dims: Vec2,
+    pub color_index: u32,
+    pub hp: u32,
+    pub strong: bool,
+}
+
+pub struct SavedBall {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    /// offset from the paddle if it was caught
+    pub attached_offset: Option<Vec2>,
+    pub speed: BallSpeed,
+}
+
+pub struct SavedMover {
+    pub pos: Vec2,
+    pub vel: Vec2,
+}
+
+/// an enemy's speed comes from where it is headed, so only that is kept
+pub struct SavedEnemy {
+    pub pos: Vec2,
+    pub target: Vec2,
+}
+
+/// centered on pos, like the explosion's CTransform
+pub struct SavedExplosion {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub frames_left: u32,
+}
+
+pub struct SavedBoss {
+    pub hp: u32,
+    pub max_hp: u32,
+    pub flash_frames: u32,
+    pub fire_countdown: u32,
+}
+
+/// everything needed to pick a run back up mid level
+/// the physics world isn't saved, every body is rebuilt from this on load
+/// with the rng where it was, a resumed run plays out the same as if it had never stopped
+pub struct RunSnapshot {
+    pub mode: GameMode,
+    pub level: u32,
+    pub lives: u32,
+    pub next_extra_life_score: u32,
+    pub score: u32,
+    pub combo: u32,
+    pub run_stats: RunStats,
+    /// the rng's seed and how many words of its stream have been used
+    pub rng: ([u8; 32], u128),
+    pub paddle_pos: Vec2,
+    pub laser_countdown: u32,
+    pub active_power_ups: Vec<ActivePowerUp>,
+    pub blocks: Vec<SavedBlock>,
+    pub balls: Vec<SavedBall>,
+    pub capsules: Vec<(SavedMover, PowerUpType)>,
+    pub bullets: Vec<SavedMover>,
+    pub enemies: Vec<SavedEnemy>,
+    /// (x, countdown) for each enemy gate, the gates themselves come back with the walls
+    pub enemy_gates: Vec<(f32, u32)>,
+    pub boss: Option<SavedBoss>,
+    pub boss_shots: Vec<SavedMover>,
+    pub explosions: Vec<SavedExplosion>,
+}
+
+pub fn has_saved_run() -> bool {
+    get_data_path(SAVE_FILE_NAME).exists()
+}
+
+/// writes the run in progress out, only makes sense while playing
+pub fn save_run(ecs: &World, state: &State) -> std::io::Result<()> {
+    let path = get_data_path(SAVE_FILE_NAME);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, snapshot_to_string(&take_snapshot(ecs, state)))
+}
+
+/// rebuilds the saved run into a fresh world and physics engine, then deletes the save
+/// nothing is touched if the save can't be read
+pub fn load_run(ecs: &mut World, state: &mut State) -> Result<(), String> {
+    let path = get_data_path(SAVE_FILE_NAME);
+    let text = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let snapshot = parse_snapshot(&text)?;
+    restore_snapshot(ecs, state, snapshot);
+    if let Err(e) = std::fs::remove_file(&path) {
+        println!("Error removing save {}: {}", path.display(), e);
+    }
+    Ok(())
+}
+
+pub fn take_snapshot(ecs: &World, state: &State) -> RunSnapshot {
+    let mut paddle: Option<Entity> = None;
+    let mut paddle_pos = Vec2::ZERO;
+    let mut laser_countdown = 0;
+    let mut active_power_ups = Vec::new();
+    for (entity, (ctransform, active, laser_fire_timer)) in ecs
+        .query::<(&CTransform, &ActivePowerUps, &LaserFireTimer)>()
+        .with::<&Paddle>()
+        .iter()
+    {
+        paddle = Some(entity);
+        paddle_pos = ctransform.pos;
+        laser_countdown = laser_fire_timer.get_countdown();
+        active_power_ups = active.active.clone();
+    }
+
+    let (score, combo) = ecs
+        .query::<(&Score, &Combo)>()
+        .iter()
+        .find(|(_, (score, _))| Some(score.owner) == paddle)
+        .map(|(_, (score, combo))| (score.score, combo.hits))
+        .unwrap_or((state.carried_score, 0));
+
+    let blocks = ecs
+        .query::<(&CTransform, &Shape, &Block, &Health, Option<&StrongBlock>)>()
+        .iter()
+        .map(
+            |(_, (ctransform, shape, block, health, strong))| SavedBlock {
+                pos: ctransform.pos,
+                dims: shape.dims,
+                color_index: block.color_index,
+                hp: health.hp,
+                strong: strong.is_some(),
+            },
+        )
+        .collect();
+    let balls = ecs
+        .query::<(&CTransform, &Physics, Option<&AttachedTo>, &BallSpeed)>()
+        .with::<&Ball>()
+        .iter()
+        .map(
+            |(_, (ctransform, physics, attached_to, ball_speed))| SavedBall {
+                pos: ctransform.pos,
+                vel: physics.vel,
+                attached_offset: attached_to.map(|attached_to| attached_to.offset),
+                speed: *ball_speed,
+            },
+        )
+        .collect();
+    let capsules = ecs
+        .query::<(&CTransform, &Physics, &PowerUp)>()
+        .iter()
+        .map(|(_, (ctransform, physics, power_up))| {
+            (
+                SavedMover {
+                    pos: ctransform.pos,
+                    vel: physics.vel,
+                },
+                power_up.power_up_type,
+            )
+        })
+        .collect();
+    let boss = ecs
+        .query::<(&Boss, &Health, &BossFireTimer)>()
+        .iter()
+        .next()
+        .map(|(_, (boss, health, boss_fire_timer))| SavedBoss {
+            hp: health.hp,
+            max_hp: boss.max_hp,
+            flash_frames: boss.flash_frames,
+            fire_countdown: boss_fire_timer.get_countdown(),
+        });
+    let boss_shots = ecs
+        .query::<(&CTransform, &Physics)>()
+        .with::<&BossProjectile>()
+        .iter()
+        .map(|(_, (ctransform, physics))| SavedMover {
+            pos: ctransform.pos,
+            vel: physics.vel,
+        })
+        .collect();
+    let explosions = ecs
+        .query::<(&CTransform, &Explosion)>()
+        .iter()
+        .map(|(_, (ctransform, explosion))| SavedExplosion {
+            pos: ctransform.pos,
+            radius: explosion.radius,
+            frames_left: explosion.frames_left,
+        })
+        .collect();
+    let bullets = ecs
+        .query::<(&CTransform, &Physics)>()
+        .with::<&Bullet>()
+        .iter()
+        .map(|(_, (ctransform, physics))| SavedMover {
+            pos: ctransform.pos,
+            vel: physics.vel,
+        })
+        .collect();
+    let enemies = ecs
+        .query::<(&CTransform, &WantsToGoTo)>()
+        .with::<&Enemy>()
+        .iter()
+        .map(|(_, (ctransform, wants_to_go_to))| SavedEnemy {
+            pos: ctransform.pos,
+            target: wants_to_go_to.pos,
+        })
+        .collect();
+    let enemy_gates = ecs
+        .query::<(&CTransform, &EnemySpawnTimer)>()
+        .with::<&EnemyGate>()
+        .iter()
+        .map(|(_, (ctransform, enemy_spawn_timer))| {
+            (ctransform.pos.x, enemy_spawn_timer.get_countdown())
+        })
+        .collect();
+
+    RunSnapshot {
+        mode: state.game_mode,
+        level: state.level,
+        lives: state.lives,
+        next_extra_life_score: state.next_extra_life_score,
+        score,
+        combo,
+        run_stats: state.run_stats.clone(),
+        rng: (state.rng.get_seed(), state.rng.get_word_pos()),
+        paddle_pos,
+        laser_countdown,
+        active_power_ups,
+        blocks,
+        balls,
+        capsules,
+        bullets,
+        enemies,
+        enemy_gates,
+        boss,
+        boss_shots,
+        explosions,
+    }
+}
+
+/// spawns everything back in through the usual archetypes, which maps each entity to its new body
+pub fn restore_snapshot(ecs: &mut World, state: &mut State, snapshot: RunSnapshot) {
+    ecs.clear();
+    state.physics = state.physics_backend.create();
+
+    state.level = snapshot.level;
+    state.lives = snapshot.lives;
+    state.next_extra_life_score = snapshot.next_extra_life_score;
+    state.carried_score = snapshot.score;
+    state.run_stats = snapshot.run_stats;
+    let (seed, word_pos) = snapshot.rng;
+    state.rng = ChaCha12Rng::from_seed(seed);
+    state.rng.set_word_pos(word_pos);
+    state.quit_menu_open = false;
+    // a resumed run can't be replayed from its seed
+    state.recording = None;
+
+    spawn_walls(ecs, state);
+    let paddle = spawn_paddle(
+        ecs,
+        state,
+        snapshot.paddle_pos,
+        BASE_PADDLE_SHAPE,
+        Color::WHITE,
+    );
+    if let Ok((active_power_ups, laser_fire_timer)) =
+        ecs.query_one_mut::<(&mut ActivePowerUps, &mut LaserFireTimer)>(paddle)
+    {
+        active_power_ups.active = snapshot.active_power_ups;
+        laser_fire_timer.set_countdown(snapshot.laser_countdown);
+    }
+    for (_, (score, combo)) in ecs.query_mut::<(&Score, &mut Combo)>() {
+        if score.owner == paddle {
+            combo.hits = snapshot.combo;
+        }
+    }
+
+    for block in snapshot.blocks {
+        spawn_block(
+            ecs,
+            state,
+            block.pos,
+            block.dims,
+            block.color_index,
+            block.hp,
+            block.strong,
+        );
+    }
+    for ball in snapshot.balls {
+        // spawn_ball puts the body's center at the position it is given
+        let entity = spawn_ball(ecs, state, ball.pos + BALL_SHAPE / 2.0, ball.vel, paddle);
+        if let Ok((ctransform, ball_speed)) =
+            ecs.query_one_mut::<(&mut CTransform, &mut BallSpeed)>(entity)
+        {
+            ctransform.pos = ball.pos;
+            *ball_speed = ball.speed;
+        }
+        if let Some(offset) = ball.attached_offset {
+            attach(ecs, state, entity, paddle, offset);
+        }
+    }
+    for (capsule, power_up_type) in snapshot.capsules {
+        spawn_powerup(ecs, state, capsule.pos, capsule.vel, power_up_type);
+    }
+    for bullet in snapshot.bullets {
+        spawn_bullet(ecs, state, bullet.pos, bullet.vel, paddle);
+    }
+    for enemy in snapshot.enemies {
+        spawn_enemy(ecs, state, enemy.pos, enemy.target);
+    }
+    for (_, (ctransform, enemy_spawn_timer)) in ecs
+        .query_mut::<(&CTransform, &mut EnemySpawnTimer)>()
+        .with::<&EnemyGate>()
+    {
+        let saved = snapshot
+            .enemy_gates
+            .iter()
+            .find(|(x, _)| (x - ctransform.pos.x).abs() < 0.5);
+        if let Some((_, countdown)) = saved {
+            enemy_spawn_timer.set_countdown(*countdown);
+        }
+    }
+    if let Some(saved) = snapshot.boss {
+        let boss = spawn_boss(ecs, state, saved.max_hp, Some(paddle));
+        if let Ok((boss, health, boss_fire_timer)) =
+            ecs.query_one_mut::<(&mut Boss, &mut Health, &mut BossFireTimer)>(boss)
+        {
+            health.hp = saved.hp;
+            boss.flash_frames = saved.flash_frames;
+            boss_fire_timer.set_countdown(saved.fire_countdown);
+        }
+    }
+    for shot in snapshot.boss_shots {
+        spawn_boss_projectile(ecs, state, shot.pos, shot.vel);
+    }
+    for saved in snapshot.explosions {
+        let explosion = spawn_explosion(ecs, saved.pos, saved.radius);
+        if let Ok(explosion) = ecs.query_one_mut::<&mut Explosion>(explosion) {
+            explosion.frames_left = saved.frames_left;
+        }
+    }
+    // the paddle size and grab zone catch up with the power ups on the first playing step
+}
+
+/// one thing per line, `key values...`
+pub fn snapshot_to_string(snapshot: &RunSnapshot) -> String {
+    let mut lines = vec![
+        SAVE_HEADER.to_string(),
+        format!("mode {:?}", snapshot.mode),
+        format!("level {}", snapshot.level),
+        format!("lives {}", snapshot.lives),
+        format!("next_extra_life_score {}", snapshot.next_extra_life_score),
+        format!("score {}", snapshot.score),
+        format!("combo {}", snapshot.combo),
+        format!(
+            "run_stats {} {} {}",
+            snapshot.run_stats.balls_lost,
+            snapshot.run_stats.power_ups_collected,
+            snapshot.run_stats.longest_combo
+        ),
+    ];
+    let (seed, word_pos) = snapshot.rng;
+    let seed: String = seed.iter().map(|byte| format!("{:02x}", byte)).collect();
+    lines.push(format!("rng {} {}", seed, word_pos));
+    for stats in snapshot.run_stats.levels.iter() {
+        lines.push(format!(
+            "level_stats {} {} {}",
+            stats.level, stats.blocks_broken, stats.frames
+        ));
+    }
+    lines.push(format!(
+        "paddle {} {}",
+        snapshot.paddle_pos.x, snapshot.paddle_pos.y
+    ));
+    lines.push(format!("laser_countdown {}", snapshot.laser_countdown));
+    for active in snapshot.active_power_ups.iter() {
+        lines.push(format!(
+            "power_up {:?} {} {}",
+            active.power_up_type, active.frames_left, active.stacks
+        ));
+    }
+    for block in snapshot.blocks.iter() {
+        lines.push(format!(
+            "block {} {} {} {} {} {} {}",
+            block.pos.x,
+            block.pos.y,
+            block.dims.x,
+            block.dims.y,
+            block.color_index,
+            block.hp,
+            block.strong as u32
+        ));
+    }
+    for ball in snapshot.balls.iter() {
+        let speed = format!(
+            "{} {} {} {}",
+            ball.speed.speed,
+            ball.speed.paddle_hits,
+            ball.speed.reached_top_wall as u32,
+            ball.speed.reached_back_rows as u32
+        );
+        match ball.attached_offset {
+            Some(offset) => lines.push(format!(
+                "attached_ball {} {} {} {} {}",
+                ball.pos.x, ball.pos.y, offset.x, offset.y, speed
+            )),
+            None => lines.push(format!(
+                "ball {} {} {} {} {}",
+                ball.pos.x, ball.pos.y, ball.vel.x, ball.vel.y, speed
+            )),
+        }
+    }
+    for (capsule, power_up_type) in snapshot.capsules.iter() {
+        lines.push(format!(
+            "capsule {} {} {} {} {:?}",
+            capsule.pos.x, capsule.pos.y, capsule.vel.x, capsule.vel.y, power_up_type
+        ));
+    }
+    for bullet in snapshot.bullets.iter() {
+        lines.push(format!(
+            "bullet {} {} {} {}",
+            bullet.pos.x, bullet.pos.y, bullet.vel.x, bullet.vel.y
+        ));
+    }
+    for enemy in snapshot.enemies.iter() {
+        lines.push(format!(
+            "enemy {} {} {} {}",
+            enemy.pos.x, enemy.pos.y, enemy.target.x, enemy.target.y
+        ));
+    }
+    for (x, countdown) in snapshot.enemy_gates.iter() {
+        lines.push(format!("enemy_gate {} {}", x, countdown));
+    }
+    if let Some(boss) = &snapshot.boss {
+        lines.push(format!(
+            "boss {} {} {} {}",
+            boss.hp, boss.max_hp, boss.flash_frames, boss.fire_countdown
+        ));
+    }
+    for shot in snapshot.boss_shots.iter() {
+        lines.push(format!(
+            "boss_shot {} {} {} {}",
+            shot.pos.x, shot.pos.y, shot.vel.x, shot.vel.y
+        ));
+    }
+    for explosion in snapshot.explosions.iter() {
+        lines.push(format!(
+            "explosion {} {} {} {}",
+            explosion.pos.x, explosion.pos.y, explosion.radius, explosion.frames_left
+        ));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+pub fn parse_snapshot(text: &str) -> Result<RunSnapshot, String> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == SAVE_HEADER => {}
+        _ => return Err(format!("expected '{}' on line 1", SAVE_HEADER)),
+    }
+
+    let mut snapshot = RunSnapshot {
+        mode: GameMode::Playing,
+        level: 1,
+        lives: 0,
+        next_extra_life_score: 0,
+        score: 0,
+        combo: 0,
+        run_stats: RunStats::new(),
+        rng: ([0; 32], 0),
+        paddle_pos: Vec2::ZERO,
+        laser_countdown: 0,
+        active_power_ups: Vec::new(),
+        blocks: Vec::new(),
+        balls: Vec::new(),
+        capsules: Vec::new(),
+        bullets: Vec::new(),
+        enemies: Vec::new(),
+        enemy_gates: Vec::new(),
+        boss: None,
+        boss_shots: Vec::new(),
+        explosions: Vec::new(),
+    };
+    for (line_index, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut values = Values {
+            parts: line.split_whitespace(),
+            line_number: line_index + 1,
+        };
+        let key = values.word()?;
+        match key {
+            "mode" => {
+                let mode = values.word()?;
+                if mode != "Playing" {
+                    return Err(format!("only Playing can be resumed, found {}", mode));
+                }
+            }
+            "level" => snapshot.level = values.number()?,
+            "lives" => snapshot.lives = values.number()?,
+            "next_extra_life_score" => snapshot.next_extra_life_score = values.number()?,
+            "score" => snapshot.score = values.number()?,
+            "combo" => snapshot.combo = values.number()?,
+            "run_stats" => {
+                snapshot.run_stats.balls_lost = values.number()?;
+                snapshot.run_stats.power_ups_collected = values.number()?;
+                snapshot.run_stats.longest_combo = values.number()?;
+            }
+            "rng" => snapshot.rng = (values.rng_seed()?, values.number()?),
+            "level_stats" => snapshot.run_stats.levels.push(LevelStats {
+                level: values.number()?,
+                blocks_broken: values.number()?,
+                frames: values.number()?,
+            }),
+            "paddle" => snapshot.paddle_pos = values.vec2()?,
+            "laser_countdown" => snapshot.laser_countdown = values.number()?,
+            "power_up" => snapshot.active_power_ups.push(ActivePowerUp {
+                power_up_type: values.power_up_type()?,
+                frames_left: values.number()?,
+                stacks: values.number()?,
+            }),
+            "block" => snapshot.blocks.push(SavedBlock {
+                pos: values.vec2()?,
+                dims: values.vec2()?,
+                color_index: values.color_index()?,
+                hp: values.number()?,
+                strong: values.number::<u32>()? != 0,
+            }),
+            "ball" => snapshot.balls.push(SavedBall {
+                pos: values.vec2()?,
+                vel: values.vec2()?,
+                attached_offset: None,
+                speed: values.ball_speed()?,
+            }),
+            "attached_ball" => snapshot.balls.push(SavedBall {
+                pos: values.vec2()?,
+                vel: Vec2::ZERO,
+                attached_offset: Some(values.vec2()?),
+                speed: values.ball_speed()?,
+            }),
+            "capsule" => snapshot.capsules.push((
+                SavedMover {
+                    pos: values.vec2()?,
+                    vel: values.vec2()?,
+                },
+                values.power_up_type()?,
+            )),
+            "bullet" => snapshot.bullets.push(SavedMover {
+                pos: values.vec2()?,
+                vel: values.vec2()?,
+            }),
+            "enemy" => snapshot.enemies.push(SavedEnemy {
+                pos: values.vec2()?,
+                target: values.vec2()?,
+            }),
+            "enemy_gate" => snapshot
+                .enemy_gates
+                .push((values.number()?, values.number()?)),
+            "boss" => {
+                snapshot.boss = Some(SavedBoss {
+                    hp: values.number()?,
+                    max_hp: values.number()?,
+                    flash_frames: values.number()?,
+                    fire_countdown: values.number()?,
+                })
+            }
+            "boss_shot" => snapshot.boss_shots.push(SavedMover {
+                pos: values.vec2()?,
+                vel: values.vec2()?,
+            }),
+            "explosion" => snapshot.explosions.push(SavedExplosion {
+                pos: values.vec2()?,
+                radius: values.number()?,
+                frames_left: values.number()?,
+            }),
+            _ => return Err(format!("line {}: unknown key '{}'", line_index + 1, key)),
+        }
+    }
+    Ok(snapshot)
+}
+
+/// the values after a key, with errors that say which line was bad
+struct Values<'a> {
+    parts: std::str::SplitWhitespace<'a>,
+    line_number: usize,
+}
+
+impl<'a> Values<'a> {
+    fn word(&mut self) -> Result<&'a str, String> {
+        self.parts
+            .next()
+            .ok_or_else(|| format!("line {}: missing a value", self.line_number))
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, String> {
+        let word = self.word()?;
+        word.parse()
+            .map_err(|_| format!("line {}: bad number '{}'", self.line_number, word))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, String> {
+        Ok(Vec2::new(self.number()?, self.number()?))
+    }
+
+    /// 32 bytes as 64 hex digits
+    fn rng_seed(&mut self) -> Result<[u8; 32], String> {
+        let word = self.word()?;
+        let bad_seed = || format!("line {}: bad rng seed '{}'", self.line_number, word);
+        if word.len() != 64 {
+            return Err(bad_seed());
+        }
+        let mut seed = [0; 32];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            let digits = word.get(i * 2..i * 2 + 2).ok_or_else(bad_seed)?;
+            *byte = u8::from_str_radix(digits, 16).map_err(|_| bad_seed())?;
+        }
+        Ok(seed)
+    }
+
+    fn ball_speed(&mut self) -> Result<BallSpeed, String> {
+        Ok(BallSpeed {
+            speed: self.number()?,
+            paddle_hits: self.number()?,
+            reached_top_wall: self.number::<u32>()? != 0,
+            reached_back_rows: self.number::<u32>()? != 0,
+        })
+    }
+
+    fn color_index(&mut self) -> Result<u32, String> {
+        let color_index: u32 = self.number()?;
+        if color_index as usize >= crate::level_data::RL_COLOR_PALETTE.len() {
+            return Err(format!(
+                "line {}: palette index {} is out of range",
+                self.line_number, color_index
+            ));
+        }
+        Ok(color_index)
+    }
+
+    fn power_up_type(&mut self) -> Result<PowerUpType, String> {
+        let word = self.word()?;
+        PowerUpType::iter()
+            .find(|power_up_type| format!("{:?}", power_up_type) == word)
+            .ok_or_else(|| format!("line {}: unknown power up '{}'", self.line_number, word))
+    }
+}
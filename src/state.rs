@@ -1,20 +1,27 @@
+use std::fmt;
+
 use glam::Vec2;
 use hecs::Entity;
-use rand::{rngs::StdRng, SeedableRng};
-use rapier2d::prelude::RigidBodyHandle;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
 
 use crate::{
     audio_playing::AudioCommandBuffer,
     components::Physics,
-    input_processing::{PlayingInputs, TitleInputs},
+    high_scores::{HighScoreTable, NameEntryState},
+    input_processing::{EditorInputs, PlayingInputs, TitleInputs},
+    levels::{get_level, load_levels, Level, LEVEL_DIRECTORY},
     message_stream::ExpiringMessages,
-    physics_engine::PhysicsEngine,
+    physics_backend::{PhysicsBackend, PhysicsBackendKind},
     render_commands::RenderCommandBuffer,
+    replay::{Replay, ReplayPlayback},
+    run_stats::{RunStats, TallyRow},
+    save_game::has_saved_run,
 };
 
 pub const FRAMES_PER_SECOND: u32 = 120;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameMode {
     Title,
     PrepareLevel,
@@ -22,15 +29,23 @@ pub enum GameMode {
     LevelComplete,
     WinGame,
     GameOver,
+    Editor,
 }
 
 pub const LEVEL_CHANGE_DELAY_DEFAULT: u32 = 10;
+pub const STARTING_LIVES: u32 = 3;
+pub const MAX_LIVES: u32 = 9;
+pub const FIRST_EXTRA_LIFE_SCORE: u32 = 20_000;
+pub const EXTRA_LIFE_SCORE_INTERVAL: u32 = 60_000;
 pub struct State {
     pub fps: f32,
     pub running: bool,
     pub time_since_last_update: f32,
     pub t: f32,
-    pub rng: StdRng,
+    /// what StdRng is underneath, used directly so a save can keep its place in the stream
+    pub rng: ChaCha12Rng,
+    /// what rng was last seeded with, so a run can be replayed
+    pub rng_seed: u64,
 
     pub game_mode: GameMode,
     pub next_game_mode: Option<GameMode>,
@@ -39,6 +54,7 @@ pub struct State {
     pub level_complete_state: Box<LevelCompleteState>,
     pub win_game_state: Box<WinGameState>,
     pub game_over_state: Box<GameOverState>,
+    pub editor_state: Box<EditorState>,
 
     pub expiring_messages: ExpiringMessages,
 
@@ -47,21 +63,58 @@ pub struct State {
 
     pub title_inputs: TitleInputs,
     pub playing_inputs: PlayingInputs,
+    pub editor_inputs: EditorInputs,
     pub mouse_screen_pos: Vec2,
+    /// asks the main loop to put the mouse back in the middle, the simulation has no window
+    pub center_mouse: bool,
 
     // pub collision_events: Vec<Collision>,
+    pub levels: Vec<Level>,
     pub level: u32,
     pub level_change_delay: u32,
+    /// escape while playing pauses on the save and quit menu
+    pub quit_menu_open: bool,
+    pub has_saved_run: bool,
+
+    pub lives: u32,
+    pub next_extra_life_score: u32,
+    /// score from previous levels, handed to the player's score when the level is spawned
+    pub carried_score: u32,
+    pub run_stats: RunStats,
+
+    pub high_scores: HighScoreTable,
+    pub name_entry: NameEntryState,
 
-    pub physics: PhysicsEngine,
+    /// the run being played, saved as the last replay when it ends
+    pub recording: Option<Replay>,
+    pub replay_playback: Option<ReplayPlayback>,
+    /// steps taken since the run started, the same as the index of the replay frame being stepped
+    pub run_frame: u32,
+
+    /// which physics the game was started with, a fresh world of it is made for each level
+    pub physics_backend: PhysicsBackendKind,
+    pub physics: Box<dyn PhysicsBackend>,
 
     pub deletion_events: Vec<DeletionEvent>,
+    /// every ball the watchdog had to fix this run, to be played back from the replay
+    pub watchdog_events: Vec<WatchdogEvent>,
 }
 
 impl State {
     pub fn new() -> Self {
-        let rng: StdRng = StdRng::from_entropy();
+        Self::with_seed(rand::random())
+    }
+
+    /// same seed and inputs give the same game every time
+    /// doesn't read the player's high scores either, they could change which mode comes next
+    pub fn new_seeded(seed: u64) -> Self {
+        let mut state = Self::with_seed(seed);
+        state.high_scores = HighScoreTable::new();
+        state
+    }
 
+    pub fn with_seed(seed: u64) -> Self {
+        let rng = ChaCha12Rng::seed_from_u64(seed);
         let game_mode = GameMode::Title;
         let transition_to: Option<GameMode> = None;
 
@@ -76,12 +129,24 @@ impl State {
         let win_game_state = Box::new(WinGameState {
             mode: WinGameMode::Announce,
             countdown: 0,
+            tally_row: 0,
+            tally_frame: 0,
+            tally_rows: Vec::new(),
         });
         let game_over_state = Box::new(GameOverState {
             mode: GameOverMode::Announce,
             countdown: 0,
         });
 
+        let levels = load_levels(std::path::Path::new(LEVEL_DIRECTORY));
+        let editor_state = Box::new(EditorState {
+            level: get_level(&levels, 1).clone(),
+            level_number: 1,
+            color_index: 1,
+            hovered_cell: None,
+            testing: false,
+        });
+
         let expiring_messages = ExpiringMessages::new();
 
         let render_command_buffer: RenderCommandBuffer = RenderCommandBuffer::new();
@@ -89,9 +154,11 @@ impl State {
 
         let title_inputs = TitleInputs::new();
         let playing_inputs = PlayingInputs::new();
+        let editor_inputs = EditorInputs::new();
         let mouse_screen_pos = Vec2::ZERO;
 
-        let physics = PhysicsEngine::new();
+        let physics_backend = PhysicsBackendKind::default();
+        let physics = physics_backend.create();
 
         let deletion_events: Vec<DeletionEvent> = Vec::new();
 
@@ -101,6 +168,7 @@ impl State {
             time_since_last_update: 0.0,
 
             rng,
+            rng_seed: seed,
 
             t: 0.0,
 
@@ -111,6 +179,7 @@ impl State {
             level_complete_state,
             win_game_state,
             game_over_state,
+            editor_state,
 
             expiring_messages,
 
@@ -119,17 +188,42 @@ impl State {
 
             title_inputs,
             playing_inputs,
+            editor_inputs,
             mouse_screen_pos,
+            center_mouse: false,
 
             // collision_events: Vec::new(),
+            levels,
             level: 1,
             level_change_delay: 0,
+            quit_menu_open: false,
+            has_saved_run: has_saved_run(),
+
+            lives: STARTING_LIVES,
+            next_extra_life_score: FIRST_EXTRA_LIFE_SCORE,
+            carried_score: 0,
+            run_stats: RunStats::new(),
+
+            high_scores: HighScoreTable::load(),
+            name_entry: NameEntryState::new(),
+
+            recording: None,
+            replay_playback: None,
+            run_frame: 0,
 
+            physics_backend,
             physics,
 
             deletion_events,
+            watchdog_events: Vec::new(),
         }
     }
+
+    /// swaps to an empty world of the given physics, the next level is built in it
+    pub fn set_physics_backend(&mut self, physics_backend: PhysicsBackendKind) {
+        self.physics_backend = physics_backend;
+        self.physics = physics_backend.create();
+    }
 }
 
 pub enum DeletionEvent {
@@ -137,6 +231,43 @@ pub enum DeletionEvent {
     Physics { entity: Entity },
 }
 
+pub enum WatchdogFix {
+    /// got all the way past this wall, put back in
+    EscapedWall { wall: &'static str },
+    /// was inside a block, pushed out
+    InsideBlock,
+    /// went sideways around this height for too long, sent off steeper
+    Looped { band_y: f32 },
+}
+
+/// a ball the physics let slip, with the replay frame to find it on
+pub struct WatchdogEvent {
+    pub run_frame: u32,
+    pub entity: Entity,
+    /// where the ball was before it was fixed
+    pub at: Vec2,
+    pub fix: WatchdogFix,
+}
+
+impl fmt::Display for WatchdogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "frame {}: {:?} ", self.run_frame, self.entity)?;
+        match self.fix {
+            WatchdogFix::EscapedWall { wall } => {
+                write!(f, "got past the {} wall at {}, put back in", wall, self.at)
+            }
+            WatchdogFix::InsideBlock => {
+                write!(f, "was inside a block at {}, pushed out", self.at)
+            }
+            WatchdogFix::Looped { band_y } => write!(
+                f,
+                "looped sideways around y {} at {}, nudged steeper",
+                band_y, self.at
+            ),
+        }
+    }
+}
+
 pub enum PrepareLevelMode {
     SpawnStuffIn,
     AnnounceLevel,
@@ -184,16 +315,24 @@ pub struct LevelCompleteState {
 pub enum WinGameMode {
     Announce,
     Announce2,
+    Tally,
+    NameEntry,
     Pause,
 }
 pub struct WinGameState {
     pub mode: WinGameMode,
     pub countdown: u32,
+    /// row of the stats tally currently counting up, rows before it are done
+    pub tally_row: usize,
+    pub tally_frame: u32,
+    /// built once when the game is won, the stats don't change after that
+    pub tally_rows: Vec<TallyRow>,
 }
 
 pub enum GameOverMode {
     Announce,
     Announce2,
+    NameEntry,
     Pause,
 }
 
@@ -202,6 +341,7 @@ impl ToString for GameOverMode {
         match self {
             GameOverMode::Announce => "Announce".to_string(),
             GameOverMode::Announce2 => "Announce2".to_string(),
+            GameOverMode::NameEntry => "NameEntry".to_string(),
             GameOverMode::Pause => "Pause".to_string(),
         }
     }
@@ -211,3 +351,15 @@ pub struct GameOverState {
     pub mode: GameOverMode,
     pub countdown: u32,
 }
+
+pub struct EditorState {
+    /// the level being edited, only copied into state.levels to test play or save
+    pub level: Level,
+    pub level_number: u32,
+    /// palette index painted by the left mouse button
+    pub color_index: u32,
+    /// (row, column) under the mouse
+    pub hovered_cell: Option<(usize, usize)>,
+    /// true while test playing, so the level leads back to the editor
+    pub testing: bool,
+}
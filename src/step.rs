@@ -1,23 +1,33 @@
 use glam::Vec2;
 use hecs::{Entity, World};
-use raylib::{prelude::Vector2, RaylibHandle};
 
 use crate::{
     audio_playing::AudioCommand,
     components::{Paddle, Player},
     entity_archetypes::spawn_ball,
+    game_mode_transitions::start_run,
+    high_scores::NameEntryState,
+    levels::{
+        get_empty_level, get_level, is_final_level, save_level, LEVEL_COLUMNS, LEVEL_DIRECTORY,
+        LEVEL_ROWS, UNBREAKABLE_COLOR_INDEX,
+    },
+    message_stream::ExpiringMessage,
+    save_game::{has_saved_run, load_run, save_run},
     state::{GameMode, GameOverMode, LevelCompleteMode, PrepareLevelMode, State, WinGameMode},
     systems::{self},
-    DIMS, TS_RATIO, WINDOW_DIMS,
+    DIMS, TS_RATIO,
 };
 
-pub fn step(rl: &mut RaylibHandle, ecs: &mut World, state: &mut State) {
+/// advances the simulation one frame, never touches the window so it can run headless
+pub fn step(ecs: &mut World, state: &mut State) {
+    // the same steps a replay records a frame for
+    let in_run = state.game_mode != GameMode::Title && !state.quit_menu_open;
     match state.game_mode {
         GameMode::Title => {
             title_step(state, ecs);
         }
         GameMode::PrepareLevel => {
-            prepare_level_step(rl, state, ecs);
+            prepare_level_step(state, ecs);
         }
         GameMode::Playing => {
             playing_step(state, ecs);
@@ -31,13 +41,39 @@ pub fn step(rl: &mut RaylibHandle, ecs: &mut World, state: &mut State) {
         GameMode::GameOver => {
             game_over_step(state, ecs);
         }
+        GameMode::Editor => {
+            editor_step(state, ecs);
+        }
+    }
+    if in_run {
+        state.run_frame += 1;
     }
 }
 
+pub const TALLY_FRAMES_PER_ROW: u32 = (30.0 * TS_RATIO) as u32;
+
+// the editor grid is squashed so all 28 rows fit on screen
+pub const EDITOR_GRID_ORIGIN: Vec2 = Vec2::new(4.0, 10.0);
+pub const EDITOR_CELL_SHAPE: Vec2 = Vec2::new(10.0, 4.0);
+pub const EDITOR_CELL_GAP: f32 = 1.0;
+pub const EDITOR_PALETTE_ORIGIN: Vec2 = Vec2::new(134.0, 24.0);
+pub const EDITOR_SWATCH_SIZE: f32 = 8.0;
+pub const EDITOR_SWATCH_SPACING: f32 = 10.0;
+pub const EDITOR_MESSAGE_LIFETIME: u32 = (120.0 * TS_RATIO) as u32;
+
 ////////////////////////    PER GAME MODE STEPPING     ////////////////////////
-pub fn title_step(state: &mut State, ecs: &mut World) {}
+pub fn title_step(state: &mut State, ecs: &mut World) {
+    if state.title_inputs.continue_run {
+        state.title_inputs.continue_run = false;
+        match load_run(ecs, state) {
+            Ok(()) => state.next_game_mode = Some(GameMode::Playing),
+            Err(e) => println!("Error loading saved run: {}", e),
+        }
+        state.has_saved_run = has_saved_run();
+    }
+}
 
-pub fn prepare_level_step(rl: &mut RaylibHandle, state: &mut State, ecs: &mut World) {
+pub fn prepare_level_step(state: &mut State, ecs: &mut World) {
     if state.prepare_level_state.countdown > 0 {
         state.prepare_level_state.countdown -= 1;
     }
@@ -80,9 +116,8 @@ pub fn prepare_level_step(rl: &mut RaylibHandle, state: &mut State, ecs: &mut Wo
         }
         PrepareLevelMode::SpawnBall => {
             if state.prepare_level_state.countdown == 0 {
-                // set mouse position
-                let center = (*WINDOW_DIMS).as_vec2();
-                rl.set_mouse_position(Vector2::new(center.x / 2.0, center.y));
+                // the main loop moves the mouse, the paddle follows it
+                state.center_mouse = true;
                 state.next_game_mode = Some(GameMode::Playing);
             }
         }
@@ -90,24 +125,56 @@ pub fn prepare_level_step(rl: &mut RaylibHandle, state: &mut State, ecs: &mut Wo
 }
 
 pub fn playing_step(state: &mut State, ecs: &mut World) {
+    // paused on the quit menu, nothing moves
+    if state.quit_menu_open {
+        if state.playing_inputs.save_and_quit {
+            match save_run(ecs, state) {
+                Ok(()) => state.running = false,
+                Err(e) => println!("Error saving run: {}", e),
+            }
+        }
+        systems::playing::rendering::render(ecs, state);
+        return;
+    }
+
     if state.level_change_delay > 0 {
         state.level_change_delay -= 1;
     }
+    state.run_stats.level_mut(state.level).frames += 1;
 
     // systems::playing::physics::constantly_resize_paddle(ecs, state);
 
     systems::playing::input_processing::process_inputs(ecs, state);
+    systems::playing::warp::update_warp_gates(ecs, state);
+    systems::playing::warp::constrain_paddles(ecs);
+    systems::playing::physics::record_position_histories(ecs);
+    systems::playing::power_ups::step_power_ups(ecs, state);
+    systems::playing::piercing_ball::update_piercing_balls(ecs, state);
+    systems::playing::lasers::fire_lasers(ecs, state);
+    systems::playing::enemy_behaviour::spawn_enemies(ecs, state);
+    systems::playing::enemy_behaviour::steer_enemies(ecs, state);
+    systems::playing::boss::face_look_at_targets(ecs);
+    systems::playing::boss::step_bosses(ecs, state);
+    systems::playing::bomb_ball::step_explosions(ecs, state);
+    systems::playing::attachment::launch_attached_balls(ecs, state);
+    systems::playing::attachment::follow_attachments(ecs);
     // systems::playing::physics::boundary_checking(ecs, state);
 
     // all reshaping needs to happen before the ecs is synced to physics
 
-    systems::playing::physics::set_ball_to_angle(ecs, state);
+    systems::playing::physics::constrain_ball_velocities(ecs, state);
     systems::playing::physics::sync_ecs_to_physics(ecs, state);
+    systems::playing::physics::sync_collider_tags(ecs, state);
     systems::playing::physics::step_physics(ecs, state);
     systems::playing::physics::respond_to_collisions(ecs, state);
+    systems::playing::ball_watchdog::ball_watchdog(ecs, state);
+    systems::playing::cleanup::remove_escaped_entities(ecs, state);
     systems::playing::cleanup::process_deletion_events(ecs, state);
+    state.physics.debug_check_entity_mapping(ecs);
     systems::playing::state_changing::check_for_level_complete(ecs, state);
+    systems::playing::warp::check_for_warp(ecs, state);
     systems::playing::state_changing::check_for_level_lost(ecs, state);
+    systems::playing::state_changing::award_extra_lives(ecs, state);
     systems::playing::rendering::render(ecs, state);
 }
 
@@ -133,6 +200,14 @@ pub fn level_complete_step(state: &mut State, ecs: &mut World) {
         }
         LevelCompleteMode::Pause => {
             if state.prepare_level_state.countdown == 0 {
+                if state.editor_state.testing {
+                    state.next_game_mode = Some(GameMode::Editor);
+                    return;
+                }
+                if is_final_level(&state.levels, state.level) {
+                    state.next_game_mode = Some(GameMode::WinGame);
+                    return;
+                }
                 state.level += 1;
                 state.next_game_mode = Some(GameMode::PrepareLevel);
             }
@@ -156,12 +231,38 @@ pub fn win_game_step(state: &mut State, ecs: &mut World) {
         }
         WinGameMode::Announce2 => {
             if state.win_game_state.countdown == 0 {
-                state.win_game_state.mode = WinGameMode::Pause;
-                state.win_game_state.countdown = (40.0 * TS_RATIO) as u32;
+                state.win_game_state.mode = WinGameMode::Tally;
+                state.win_game_state.tally_row = 0;
+                state.win_game_state.tally_frame = 0;
             }
         }
+        WinGameMode::Tally => {
+            // count each row up in turn, then linger on the finished table
+            // confirm skips straight to the finished table
+            let num_rows = state.win_game_state.tally_rows.len();
+            state.win_game_state.tally_frame += 1;
+            if state.title_inputs.confirm {
+                state.win_game_state.tally_row = num_rows;
+                state.audio_command_buffer.push(AudioCommand::TallyRow);
+            } else if state.win_game_state.tally_frame >= TALLY_FRAMES_PER_ROW {
+                state.win_game_state.tally_row += 1;
+                state.win_game_state.tally_frame = 0;
+                state.audio_command_buffer.push(AudioCommand::TallyRow);
+            }
+            if state.win_game_state.tally_row >= num_rows {
+                if state.high_scores.qualifies(state.carried_score) {
+                    state.win_game_state.mode = WinGameMode::NameEntry;
+                    state.name_entry = NameEntryState::new();
+                } else {
+                    state.win_game_state.mode = WinGameMode::Pause;
+                    state.win_game_state.countdown = (300.0 * TS_RATIO) as u32;
+                }
+            }
+        }
+        // left by input processing once the name is entered
+        WinGameMode::NameEntry => {}
         WinGameMode::Pause => {
-            if state.prepare_level_state.countdown == 0 {
+            if state.win_game_state.countdown == 0 {
                 state.next_game_mode = Some(GameMode::Title);
             }
         }
@@ -184,14 +285,135 @@ pub fn game_over_step(state: &mut State, ecs: &mut World) {
         }
         GameOverMode::Announce2 => {
             if state.game_over_state.countdown == 0 {
-                state.game_over_state.mode = GameOverMode::Pause;
-                state.game_over_state.countdown = (40.0 * TS_RATIO) as u32;
+                // test plays don't go on the high score table
+                if state.high_scores.qualifies(state.carried_score) && !state.editor_state.testing {
+                    state.game_over_state.mode = GameOverMode::NameEntry;
+                    state.name_entry = NameEntryState::new();
+                } else {
+                    state.game_over_state.mode = GameOverMode::Pause;
+                    state.game_over_state.countdown = (40.0 * TS_RATIO) as u32;
+                }
             }
         }
+        // left by input processing once the name is entered
+        GameOverMode::NameEntry => {}
         GameOverMode::Pause => {
-            if state.prepare_level_state.countdown == 0 {
-                state.next_game_mode = Some(GameMode::Title);
+            if state.game_over_state.countdown == 0 {
+                state.next_game_mode = if state.editor_state.testing {
+                    Some(GameMode::Editor)
+                } else {
+                    Some(GameMode::Title)
+                };
             }
         }
     }
 }
+
+pub fn editor_step(state: &mut State, ecs: &mut World) {
+    for message in state.expiring_messages.iter_mut() {
+        message.lifetime = message.lifetime.saturating_sub(1);
+    }
+    state
+        .expiring_messages
+        .retain(|message| message.lifetime > 0);
+
+    let mouse_pos = state.mouse_screen_pos;
+    state.editor_state.hovered_cell = get_editor_cell(mouse_pos);
+
+    if let Some(color_index) = state.editor_inputs.color_index {
+        state.editor_state.color_index = color_index;
+    }
+    if state.editor_inputs.paint {
+        if let Some((row, column)) = state.editor_state.hovered_cell {
+            state.editor_state.level.grid[row][column] = state.editor_state.color_index;
+        } else if let Some(color_index) = get_editor_swatch(mouse_pos) {
+            state.editor_state.color_index = color_index;
+        }
+    }
+    if state.editor_inputs.erase {
+        if let Some((row, column)) = state.editor_state.hovered_cell {
+            state.editor_state.level.grid[row][column] = 0;
+        }
+    }
+
+    // switching levels drops anything not test played or saved
+    let level_number = state.editor_state.level_number;
+    if state.editor_inputs.previous_level && level_number > 1 {
+        state.editor_state.level_number -= 1;
+        state.editor_state.level = get_level(&state.levels, level_number - 1).clone();
+    }
+    if state.editor_inputs.next_level && (level_number as usize) < state.levels.len() {
+        state.editor_state.level_number += 1;
+        state.editor_state.level = get_level(&state.levels, level_number + 1).clone();
+    }
+    if state.editor_inputs.new_level {
+        let new_level_number = state.levels.len() as u32 + 1;
+        state.editor_state.level_number = new_level_number;
+        state.editor_state.level = get_empty_level(format!("round {}", new_level_number));
+    }
+
+    if state.editor_inputs.test_play {
+        store_editor_level(state);
+        state.editor_state.testing = true;
+        start_run(state, rand::random(), state.editor_state.level_number);
+    }
+    if state.editor_inputs.save {
+        store_editor_level(state);
+        let text = match save_level(
+            std::path::Path::new(LEVEL_DIRECTORY),
+            state.editor_state.level_number,
+            &state.editor_state.level,
+        ) {
+            Ok(path) => format!("saved {}", path.display()),
+            Err(e) => {
+                println!("Error saving level: {}", e);
+                "save failed".to_string()
+            }
+        };
+        state.expiring_messages.push(ExpiringMessage {
+            text,
+            lifetime: EDITOR_MESSAGE_LIFETIME,
+        });
+    }
+    if state.editor_inputs.exit {
+        state.next_game_mode = Some(GameMode::Title);
+    }
+}
+
+/// puts the edited level into the level list, appending it if it is new
+pub fn store_editor_level(state: &mut State) {
+    let index = state.editor_state.level_number as usize - 1;
+    let level = state.editor_state.level.clone();
+    if index < state.levels.len() {
+        state.levels[index] = level;
+    } else {
+        state.levels.push(level);
+        state.editor_state.level_number = state.levels.len() as u32;
+    }
+}
+
+/// (row, column) of the editor grid cell at a screen position
+pub fn get_editor_cell(pos: Vec2) -> Option<(usize, usize)> {
+    let cell = ((pos - EDITOR_GRID_ORIGIN) / (EDITOR_CELL_SHAPE + EDITOR_CELL_GAP)).floor();
+    if cell.x < 0.0 || cell.y < 0.0 {
+        return None;
+    }
+    let (row, column) = (cell.y as usize, cell.x as usize);
+    if row >= LEVEL_ROWS || column >= LEVEL_COLUMNS {
+        return None;
+    }
+    Some((row, column))
+}
+
+/// palette index of the swatch at a screen position, the empty colour has no swatch
+pub fn get_editor_swatch(pos: Vec2) -> Option<u32> {
+    let offset = pos - EDITOR_PALETTE_ORIGIN;
+    if offset.x < 0.0 || offset.y < 0.0 || offset.y > EDITOR_SWATCH_SIZE {
+        return None;
+    }
+    let color_index = (offset.x / EDITOR_SWATCH_SPACING) as u32 + 1;
+    if color_index > UNBREAKABLE_COLOR_INDEX {
+        return None;
+    }
+    Some(color_index)
+}
@@ -0,0 +1,152 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+
+use crate::{
+    components::{
+        Attachable, AttachedTo, Ball, BallSpeed, CTransform, GrabZone, HasRigidBody, Physics,
+        PositionManaged, Shape, VelocityManaged,
+    },
+    physics_backend::BodyKind,
+    state::State,
+    systems::playing::physics::get_ball_speed,
+};
+
+/// how far past the paddle's edges a ball can land and still be caught
+pub const GRAB_ZONE_RADIUS: f32 = 2.0;
+/// launch angle from vertical for a ball released from the very edge of the paddle
+const MAX_LAUNCH_ANGLE: f32 = std::f32::consts::PI / 3.0;
+
+/// called when a ball touches a paddle, returns true if the paddle caught it
+pub fn try_catch_ball(ecs: &mut World, state: &mut State, ball: Entity, paddle: Entity) -> bool {
+    if !ecs.satisfies::<(&Ball, &Attachable)>(ball).unwrap_or(false)
+        || ecs.satisfies::<&AttachedTo>(ball).unwrap_or(false)
+    {
+        return false;
+    }
+
+    let Ok(mut paddle_query) = ecs.query_one::<(&CTransform, &Shape, &GrabZone)>(paddle) else {
+        return false;
+    };
+    let Some((paddle_ctransform, paddle_shape, grab_zone)) = paddle_query.get() else {
+        return false;
+    };
+    let Ok(mut ball_query) = ecs.query_one::<(&CTransform, &Shape)>(ball) else {
+        return false;
+    };
+    let Some((ball_ctransform, ball_shape)) = ball_query.get() else {
+        return false;
+    };
+
+    let ball_center_x = ball_ctransform.pos.x + ball_shape.dims.x / 2.0;
+    if ball_center_x < paddle_ctransform.pos.x - grab_zone.radius
+        || ball_center_x > paddle_ctransform.pos.x + paddle_shape.dims.x + grab_zone.radius
+    {
+        return false;
+    }
+
+    // park the ball just above the paddle so it is not touching it when launched
+    let offset = Vec2::new(
+        (ball_ctransform.pos.x - paddle_ctransform.pos.x).clamp(
+            -ball_shape.dims.x / 2.0,
+            paddle_shape.dims.x - ball_shape.dims.x / 2.0,
+        ),
+        -(ball_shape.dims.y + 1.0),
+    );
+    drop(paddle_query);
+    drop(ball_query);
+
+    attach(ecs, state, ball, paddle, offset);
+    true
+}
+
+/// swaps a ball over to following the paddle, with its body made kinematic so the physics leaves it alone
+pub fn attach(ecs: &mut World, state: &mut State, ball: Entity, paddle: Entity, offset: Vec2) {
+    let _ = ecs.remove_one::<VelocityManaged>(ball);
+    let _ = ecs.insert(
+        ball,
+        (
+            AttachedTo {
+                entity: paddle,
+                offset,
+            },
+            PositionManaged,
+        ),
+    );
+    if let Ok(physics) = ecs.query_one_mut::<&mut Physics>(ball) {
+        physics.vel = Vec2::ZERO;
+    }
+
+    if let Ok(has_rigid_body) = ecs.get::<&HasRigidBody>(ball) {
+        state
+            .physics
+            .set_kind(has_rigid_body.handle, BodyKind::KinematicPosition);
+        state.physics.set_vel(has_rigid_body.handle, Vec2::ZERO);
+    }
+}
+
+/// hands a ball back to the physics engine moving at the given velocity
+pub fn detach(ecs: &mut World, state: &mut State, ball: Entity, vel: Vec2) {
+    let _ = ecs.remove::<(AttachedTo, PositionManaged)>(ball);
+    let _ = ecs.insert_one(ball, VelocityManaged);
+    if let Ok(physics) = ecs.query_one_mut::<&mut Physics>(ball) {
+        physics.vel = vel;
+    }
+
+    if let Ok(has_rigid_body) = ecs.get::<&HasRigidBody>(ball) {
+        state
+            .physics
+            .set_kind(has_rigid_body.handle, BodyKind::Dynamic);
+        state.physics.set_vel(has_rigid_body.handle, vel);
+    }
+}
+
+/// attached balls are released on confirm, or when the paddle stops catching
+/// the further from the paddle's center the ball sits, the flatter it is launched
+pub fn launch_attached_balls(ecs: &mut World, state: &mut State) {
+    let mut launches: Vec<(Entity, Vec2)> = Vec::new();
+    for (entity, (attached_to, shape, ball_speed)) in ecs
+        .query::<(&AttachedTo, &Shape, Option<&BallSpeed>)>()
+        .iter()
+    {
+        let ball_speed = ball_speed.map_or(get_ball_speed(state), |ball_speed| ball_speed.speed);
+        let paddle = ecs
+            .query_one::<(&Shape, Option<&GrabZone>)>(attached_to.entity)
+            .ok()
+            .and_then(|mut paddle_query| {
+                paddle_query
+                    .get()
+                    .map(|(paddle_shape, grab_zone)| (paddle_shape.dims, grab_zone.is_some()))
+            });
+        let Some((paddle_dims, catching)) = paddle else {
+            launches.push((entity, Vec2::new(0.0, -ball_speed)));
+            continue;
+        };
+        if catching && !state.playing_inputs.confirm {
+            continue;
+        }
+
+        let half_width = paddle_dims.x / 2.0;
+        let from_center = attached_to.offset.x + shape.dims.x / 2.0 - half_width;
+        let angle = (from_center / half_width).clamp(-1.0, 1.0) * MAX_LAUNCH_ANGLE;
+        launches.push((entity, Vec2::new(angle.sin(), -angle.cos()) * ball_speed));
+    }
+
+    for (ball, vel) in launches {
+        detach(ecs, state, ball, vel);
+    }
+}
+
+/// keeps attached entities riding along with whatever they are attached to
+pub fn follow_attachments(ecs: &mut World) {
+    let mut new_positions: Vec<(Entity, Vec2)> = Vec::new();
+    for (entity, attached_to) in ecs.query::<&AttachedTo>().iter() {
+        if let Ok(parent) = ecs.get::<&CTransform>(attached_to.entity) {
+            new_positions.push((entity, parent.pos + attached_to.offset));
+        }
+    }
+    for (entity, pos) in new_positions {
+        if let Ok(ctransform) = ecs.query_one_mut::<&mut CTransform>(entity) {
+            ctransform.pos = pos;
+        }
+    }
+}
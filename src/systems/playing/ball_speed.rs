@@ -0,0 +1,98 @@
+use hecs::{Entity, World};
+
+use crate::{
+    components::{BallSpeed, OwnedBy},
+    game_mode_transitions::get_block_row,
+    levels::{get_level, FIRST_PLAYED_ROW},
+    state::State,
+    systems::playing::physics::get_ball_speed,
+    TS_RATIO,
+};
+
+/// each speed up adds this much of the level's base speed
+pub const SPEED_UP_STEP: f32 = 0.1;
+/// every this many paddle hits the ball speeds up
+pub const PADDLE_HITS_PER_SPEED_UP: u32 = 8;
+/// blocks in grid rows above this speed the ball up the first time it reaches them
+pub const BACK_ROWS_END: usize = FIRST_PLAYED_ROW + 4;
+/// picking up SpeedUp scales the ball's speed by this much
+pub const SPEED_UP_POWER_UP_SCALE: f32 = 1.25;
+
+pub fn get_max_ball_speed(state: &State) -> f32 {
+    let level = get_level(&state.levels, state.level);
+    level.max_ball_speed.max(level.ball_speed) * (1.0 / TS_RATIO)
+}
+
+pub fn new_ball_speed(state: &State) -> BallSpeed {
+    BallSpeed {
+        speed: get_ball_speed(state),
+        paddle_hits: 0,
+        reached_top_wall: false,
+        reached_back_rows: false,
+    }
+}
+
+/// the ball's own speed, or the level's base speed if it doesn't track one
+pub fn get_speed_of_ball(ecs: &World, state: &State, ball: Entity) -> f32 {
+    ecs.get::<&BallSpeed>(ball)
+        .map(|ball_speed| ball_speed.speed)
+        .unwrap_or_else(|_| get_ball_speed(state))
+}
+
+fn speed_up(ball_speed: &mut BallSpeed, state: &State) {
+    ball_speed.speed =
+        (ball_speed.speed + get_ball_speed(state) * SPEED_UP_STEP).min(get_max_ball_speed(state));
+}
+
+pub fn count_paddle_hit(ecs: &mut World, state: &State, ball: Entity) {
+    if let Ok(ball_speed) = ecs.query_one_mut::<&mut BallSpeed>(ball) {
+        ball_speed.paddle_hits += 1;
+        if ball_speed.paddle_hits % PADDLE_HITS_PER_SPEED_UP == 0 {
+            speed_up(ball_speed, state);
+        }
+    }
+}
+
+pub fn reach_top_wall(ecs: &mut World, state: &State, ball: Entity) {
+    if let Ok(ball_speed) = ecs.query_one_mut::<&mut BallSpeed>(ball) {
+        if !ball_speed.reached_top_wall {
+            ball_speed.reached_top_wall = true;
+            speed_up(ball_speed, state);
+        }
+    }
+}
+
+pub fn reach_block_row(ecs: &mut World, state: &State, ball: Entity, block_y: f32) {
+    if get_block_row(block_y) >= BACK_ROWS_END {
+        return;
+    }
+    if let Ok(ball_speed) = ecs.query_one_mut::<&mut BallSpeed>(ball) {
+        if !ball_speed.reached_back_rows {
+            ball_speed.reached_back_rows = true;
+            speed_up(ball_speed, state);
+        }
+    }
+}
+
+/// SpeedUp and SlowDown act on the speed of every ball the paddle owns
+/// slowing down drops the ball back to the level's base speed
+pub fn speed_up_owned_balls(ecs: &mut World, state: &State, owner: Entity) {
+    let max_speed = get_max_ball_speed(state);
+    for ball_speed in owned_ball_speeds(ecs, owner) {
+        ball_speed.speed = (ball_speed.speed * SPEED_UP_POWER_UP_SCALE).min(max_speed);
+    }
+}
+
+pub fn slow_down_owned_balls(ecs: &mut World, state: &State, owner: Entity) {
+    let base_speed = get_ball_speed(state);
+    for ball_speed in owned_ball_speeds(ecs, owner) {
+        ball_speed.speed = base_speed;
+    }
+}
+
+fn owned_ball_speeds(ecs: &mut World, owner: Entity) -> impl Iterator<Item = &mut BallSpeed> {
+    ecs.query_mut::<(&mut BallSpeed, &OwnedBy)>()
+        .into_iter()
+        .filter(move |(_, (_, owned_by))| owned_by.owner == owner)
+        .map(|(_, (ball_speed, _))| ball_speed)
+}
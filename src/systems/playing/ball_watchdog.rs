@@ -0,0 +1,228 @@
+use glam::Vec2;
+use hecs::World;
+
+use crate::{
+    collision::{Aabb, Tlbr},
+    components::{
+        AttachedTo, Ball, Block, CTransform, HasRigidBody, Health, LoopWatch, Physics, Piercing,
+        Shape, StrongBlock,
+    },
+    physics_backend::{BodyHandle, PHYSICS_DT},
+    state::{State, WatchdogEvent, WatchdogFix, FRAMES_PER_SECOND},
+    DIMS,
+};
+
+/// a ball that keeps within this many pixels up or down is going round in a horizontal loop
+pub const LOOP_BAND_HEIGHT: f32 = 12.0;
+pub const LOOP_FRAMES: u32 = 5 * FRAMES_PER_SECOND;
+/// how much steeper a looping ball is sent off
+pub const LOOP_NUDGE_ANGLE: f32 = std::f32::consts::PI / 12.0;
+
+/// catches balls the physics let slip and puts them back where they could have been
+/// runs after collisions are handled, so a ball lost this step is already gone
+/// each fix is kept in the state's watchdog events with the replay frame it happened on
+pub fn ball_watchdog(ecs: &mut World, state: &mut State) {
+    let blocks: Vec<(Tlbr, bool)> = ecs
+        .query::<(&CTransform, &Shape, &Health, Option<&StrongBlock>)>()
+        .with::<&Block>()
+        .iter()
+        .filter(|(_, (_, _, health, _))| health.hp > 0)
+        .map(|(_, (ctransform, shape, _, strong_block))| {
+            (
+                Aabb::new(ctransform.pos, shape.dims).get_bounds(),
+                strong_block.is_some(),
+            )
+        })
+        .collect();
+
+    let mut corrections: Vec<(BodyHandle, Vec2, Vec2)> = Vec::new();
+    for (entity, (ctransform, shape, physics, loop_watch, piercing, has_rigid_body)) in ecs
+        .query_mut::<(
+            &mut CTransform,
+            &Shape,
+            &mut Physics,
+            &mut LoopWatch,
+            Option<&Piercing>,
+            &HasRigidBody,
+        )>()
+        .with::<&Ball>()
+        .without::<&AttachedTo>()
+    {
+        let was_at = ctransform.pos;
+        let mut fixes: Vec<WatchdogFix> = Vec::new();
+
+        if let Some(wall) = return_to_field(&mut ctransform.pos, &mut physics.vel, shape.dims) {
+            fixes.push(WatchdogFix::EscapedWall { wall });
+        } else if push_out_of_blocks(
+            &mut ctransform.pos,
+            &mut physics.vel,
+            shape.dims,
+            piercing.is_some(),
+            &blocks,
+        ) {
+            fixes.push(WatchdogFix::InsideBlock);
+        }
+
+        let center_y = ctransform.pos.y + shape.dims.y / 2.0;
+        if nudge_out_of_loop(loop_watch, center_y, &mut physics.vel) {
+            fixes.push(WatchdogFix::Looped {
+                band_y: loop_watch.band_y,
+            });
+        }
+
+        if !fixes.is_empty() {
+            corrections.push((
+                has_rigid_body.handle,
+                ctransform.pos + shape.dims / 2.0,
+                physics.vel,
+            ));
+        }
+        for fix in fixes {
+            state.watchdog_events.push(WatchdogEvent {
+                run_frame: state.run_frame,
+                entity,
+                at: was_at,
+                fix,
+            });
+        }
+    }
+
+    // the ball's position comes from its body, so the body has to be moved too
+    for (body, center, vel) in corrections {
+        state.physics.set_center(body, center);
+        state.physics.set_vel(body, vel);
+    }
+}
+
+/// the inside of the walls, a ball is only out once it is entirely past one
+fn get_field() -> Tlbr {
+    Tlbr::new(
+        Vec2::ONE,
+        Vec2::new(DIMS.x as f32 - 1.0, DIMS.y as f32 - 1.0),
+    )
+}
+
+/// moves a ball that got all the way past a wall back just inside it, heading back into the field
+/// except past the bottom, where it keeps falling so the ball eater still gets it
+/// a ball only partly into a wall is still bouncing off it
+/// returns which wall it got past
+fn return_to_field(pos: &mut Vec2, vel: &mut Vec2, dims: Vec2) -> Option<&'static str> {
+    let field = get_field();
+    if pos.x + dims.x < field.tl.x {
+        pos.x = field.tl.x;
+        vel.x = vel.x.abs();
+        Some("left")
+    } else if pos.x > field.br.x {
+        pos.x = field.br.x - dims.x;
+        vel.x = -vel.x.abs();
+        Some("right")
+    } else if pos.y + dims.y < field.tl.y {
+        pos.y = field.tl.y;
+        vel.y = vel.y.abs();
+        Some("top")
+    } else if pos.y > field.br.y {
+        pos.y = field.br.y - dims.y;
+        vel.y = vel.y.abs();
+        Some("bottom")
+    } else {
+        None
+    }
+}
+
+/// moves a ball that has got inside a block out to the nearest free spot, heading away from it
+/// a ball can sink up to a step's travel into a block before bouncing, any deeper and it is inside
+/// a piercing ball belongs inside breakable blocks, just not strong ones
+fn push_out_of_blocks(
+    pos: &mut Vec2,
+    vel: &mut Vec2,
+    dims: Vec2,
+    piercing: bool,
+    blocks: &[(Tlbr, bool)],
+) -> bool {
+    let solid: Vec<Tlbr> = blocks
+        .iter()
+        .filter(|(_, strong)| *strong || !piercing)
+        .map(|(block, _)| *block)
+        .collect();
+    let ball = Aabb::new(*pos, dims).get_bounds();
+    let travel = vel.length() * PHYSICS_DT;
+    let inside = solid
+        .iter()
+        .any(|block| ball.overlaps(block) && get_depth(&ball, block) > travel);
+    if !inside {
+        return false;
+    }
+
+    // blocks sit flush against each other, so getting out of one can mean going through its neighbours
+    // a way out that ends up in a wall is only taken if there is no other
+    let field = get_field();
+    let ways_out = [(0, 1.0), (0, -1.0), (1, 1.0), (1, -1.0)].map(|(axis, dir)| {
+        let distance = get_distance_out(&ball, axis, dir, &solid);
+        let mut moved = *pos;
+        moved[axis] += distance * dir;
+        let in_field = moved.cmpge(field.tl).all() && (moved + dims).cmple(field.br).all();
+        (axis, dir, distance, in_field)
+    });
+    let Some((axis, dir, distance, _)) = ways_out
+        .into_iter()
+        .min_by(|a, b| b.3.cmp(&a.3).then(a.2.total_cmp(&b.2)))
+    else {
+        return false;
+    };
+    pos[axis] += distance * dir;
+    vel[axis] = vel[axis].abs() * dir;
+    true
+}
+
+/// how far the ball is into the block, the shortest way out of it
+fn get_depth(ball: &Tlbr, block: &Tlbr) -> f32 {
+    [
+        ball.br.x - block.tl.x,
+        block.br.x - ball.tl.x,
+        ball.br.y - block.tl.y,
+        block.br.y - ball.tl.y,
+    ]
+    .into_iter()
+    .fold(f32::MAX, f32::min)
+}
+
+/// how far the ball has to go along the axis, dir being 1 or -1, to be clear of every block
+/// each block it is in carries it past that block's far side, into the next or out
+fn get_distance_out(ball: &Tlbr, axis: usize, dir: f32, blocks: &[Tlbr]) -> f32 {
+    let mut distance = 0.0;
+    loop {
+        let mut moved = *ball;
+        moved.tl[axis] += distance * dir;
+        moved.br[axis] += distance * dir;
+        let Some(block) = blocks.iter().find(|block| moved.overlaps(block)) else {
+            return distance;
+        };
+        distance += if dir > 0.0 {
+            block.br[axis] - moved.tl[axis]
+        } else {
+            moved.br[axis] - block.tl[axis]
+        };
+    }
+}
+
+/// a ball that hasn't left its band for LOOP_FRAMES is turned away from horizontal,
+/// keeping the way it was heading, and watched again from scratch
+fn nudge_out_of_loop(loop_watch: &mut LoopWatch, center_y: f32, vel: &mut Vec2) -> bool {
+    if (center_y - loop_watch.band_y).abs() > LOOP_BAND_HEIGHT / 2.0 {
+        loop_watch.band_y = center_y;
+        loop_watch.frames = 0;
+        return false;
+    }
+    loop_watch.frames += 1;
+    if loop_watch.frames < LOOP_FRAMES {
+        return false;
+    }
+    loop_watch.frames = 0;
+    let steeper = if vel.x * vel.y >= 0.0 {
+        LOOP_NUDGE_ANGLE
+    } else {
+        -LOOP_NUDGE_ANGLE
+    };
+    *vel = Vec2::from_angle(steeper).rotate(*vel);
+    true
+}
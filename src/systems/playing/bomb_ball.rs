@@ -0,0 +1,74 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+
+use crate::{
+    audio_playing::AudioCommand,
+    components::{ActivePowerUps, Block, CTransform, Explosion, OwnedBy, PowerUpType, Shape},
+    entity_archetypes::spawn_explosion,
+    state::{DeletionEvent, State, FRAMES_PER_SECOND},
+    systems::playing::{collisions::CollisionContext, physics::damage_block},
+};
+
+/// anything with a corner inside this reach of the hit block's center takes a hit
+pub const BOMB_RADIUS: f32 = 16.0;
+pub const EXPLOSION_FRAMES: u32 = FRAMES_PER_SECOND / 2;
+
+/// spends one of the owner's bomb stacks, if it has any left
+/// true if the ball went off
+pub fn try_detonate_ball(ecs: &mut World, ball: Entity) -> bool {
+    let Ok(owner) = ecs.get::<&OwnedBy>(ball).map(|owned_by| owned_by.owner) else {
+        return false;
+    };
+    let Ok(active_power_ups) = ecs.query_one_mut::<&mut ActivePowerUps>(owner) else {
+        return false;
+    };
+    let Some(bomb) = active_power_ups
+        .active
+        .iter_mut()
+        .find(|active| active.power_up_type == PowerUpType::BombBall && active.stacks > 0)
+    else {
+        return false;
+    };
+    // step_power_ups clears it out once the last stack is gone
+    bomb.stacks -= 1;
+    true
+}
+
+/// damages every block the blast reaches, strong blocks included
+/// the hitter's owner is credited with everything it breaks
+pub fn explode(
+    ecs: &mut World,
+    state: &mut State,
+    context: &mut CollisionContext,
+    hitter: Entity,
+    center: Vec2,
+) {
+    state.audio_command_buffer.push(AudioCommand::Explosion);
+    spawn_explosion(ecs, center, BOMB_RADIUS);
+
+    let blocks: Vec<Entity> = ecs
+        .query::<(&CTransform, &Shape)>()
+        .with::<&Block>()
+        .iter()
+        .filter(|(_, (ctransform, shape))| {
+            let closest = center.clamp(ctransform.pos, ctransform.pos + shape.dims);
+            closest.distance(center) <= BOMB_RADIUS
+        })
+        .map(|(entity, _)| entity)
+        .collect();
+    for block in blocks {
+        if let Some(block_center) = damage_block(ecs, state, hitter, block) {
+            context.destroyed_blocks.push(block_center);
+        }
+    }
+}
+
+/// blasts only hang around long enough to be seen
+pub fn step_explosions(ecs: &mut World, state: &mut State) {
+    for (entity, explosion) in ecs.query_mut::<&mut Explosion>() {
+        explosion.frames_left = explosion.frames_left.saturating_sub(1);
+        if explosion.frames_left == 0 {
+            state.deletion_events.push(DeletionEvent::Entity { entity });
+        }
+    }
+}
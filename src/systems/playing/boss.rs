@@ -0,0 +1,176 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+
+use crate::{
+    audio_playing::AudioCommand,
+    components::{Boss, BossProjectile, CTransform, Health, LookAt, OwnedBy, Shape},
+    entity_archetypes::{spawn_boss_projectile, BOSS_PROJECTILE_SHAPE},
+    state::{DeletionEvent, State, FRAMES_PER_SECOND},
+    systems::playing::{
+        collisions::CollisionContext, lasers::spend_bullet, scoring::add_points,
+        state_changing::lose_life,
+    },
+    timer::BossFireTimer,
+    TS_RATIO,
+};
+
+pub const BOSS_FIRE_INTERVAL: u32 = 4 * FRAMES_PER_SECOND;
+pub const BOSS_FLASH_FRAMES: u32 = (6.0 * TS_RATIO) as u32;
+pub const BOSS_PROJECTILE_SPEED: f32 = 60.0 * (1.0 / TS_RATIO);
+pub const BOSS_HIT_POINTS: u32 = 100;
+pub const BOSS_DEFEAT_POINTS: u32 = 10_000;
+
+/// turns anything with a LookAt to face the center of what it is looking at
+pub fn face_look_at_targets(ecs: &mut World) {
+    for (_, (ctransform, shape, look_at)) in
+        ecs.query::<(&CTransform, &Shape, &mut LookAt)>().iter()
+    {
+        let Ok(mut target) = ecs.query_one::<(&CTransform, &Shape)>(look_at.entity) else {
+            continue;
+        };
+        let Some((target_ctransform, target_shape)) = target.get() else {
+            continue;
+        };
+        let from = ctransform.pos + shape.dims / 2.0;
+        let to = target_ctransform.pos + target_shape.dims / 2.0;
+        if let Some(facing) = (to - from).try_normalize() {
+            look_at.facing = facing;
+        }
+    }
+}
+
+/// counts down the hit flash and fires at whatever the boss is facing
+pub fn step_bosses(ecs: &mut World, state: &mut State) {
+    let mut shots: Vec<(Vec2, Vec2)> = Vec::new();
+    for (_, (boss, ctransform, shape, look_at, boss_fire_timer)) in ecs
+        .query::<(&mut Boss, &CTransform, &Shape, &LookAt, &mut BossFireTimer)>()
+        .iter()
+    {
+        boss.flash_frames = boss.flash_frames.saturating_sub(1);
+        boss_fire_timer.step();
+        if boss_fire_timer.get_countdown() > 0 {
+            continue;
+        }
+        boss_fire_timer.reset();
+        let center = ctransform.pos + shape.dims / 2.0;
+        shots.push((
+            center - BOSS_PROJECTILE_SHAPE / 2.0,
+            look_at.facing * BOSS_PROJECTILE_SPEED,
+        ));
+    }
+
+    for (pos, vel) in shots {
+        spawn_boss_projectile(ecs, state, pos, vel);
+    }
+}
+
+pub fn ball_hit_boss(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    ball: Entity,
+    boss: Entity,
+) {
+    state
+        .audio_command_buffer
+        .push(AudioCommand::BallSturdyBlockBounce);
+    damage_boss(ecs, state, ball, boss);
+}
+
+pub fn bullet_hit_boss(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    bullet: Entity,
+    boss: Entity,
+) {
+    damage_boss(ecs, state, bullet, boss);
+    spend_bullet(ecs, state, bullet);
+}
+
+pub fn projectile_hit_paddle(
+    _ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    projectile: Entity,
+    _paddle: Entity,
+) {
+    state.audio_command_buffer.push(AudioCommand::BallDrop);
+    state
+        .deletion_events
+        .push(DeletionEvent::Entity { entity: projectile });
+    state
+        .deletion_events
+        .push(DeletionEvent::Physics { entity: projectile });
+    // a second hit while the life is already lost changes nothing
+    if state.next_game_mode.is_none() {
+        lose_life(state);
+    }
+}
+
+/// takes one hp off the boss and flashes it, the hitter's owner gets the points
+fn damage_boss(ecs: &mut World, state: &mut State, hitter: Entity, boss: Entity) {
+    let Ok((boss_state, health)) = ecs.query_one_mut::<(&mut Boss, &mut Health)>(boss) else {
+        return;
+    };
+    if health.hp == 0 {
+        return;
+    }
+    health.hp -= 1;
+    boss_state.flash_frames = BOSS_FLASH_FRAMES;
+    let defeated = health.hp == 0;
+
+    if let Ok(owner) = ecs.get::<&OwnedBy>(hitter).map(|owned_by| owned_by.owner) {
+        let points = if defeated {
+            BOSS_DEFEAT_POINTS
+        } else {
+            BOSS_HIT_POINTS
+        };
+        add_points(ecs, owner, points);
+    }
+
+    if defeated {
+        despawn_boss(ecs, state, boss);
+    }
+}
+
+/// the boss stops counting as soon as it is beaten, its body goes with the other deletions
+fn despawn_boss(ecs: &mut World, state: &mut State, boss: Entity) {
+    let _ = ecs.remove_one::<Boss>(boss);
+    let _ = ecs.remove_one::<LookAt>(boss);
+    state
+        .deletion_events
+        .push(DeletionEvent::Entity { entity: boss });
+    state
+        .deletion_events
+        .push(DeletionEvent::Physics { entity: boss });
+}
+
+/// clears the boss and its shots, for when the level is respawned
+pub fn delete_all_bosses(ecs: &mut World, state: &mut State) {
+    let bosses: Vec<Entity> = ecs
+        .query::<()>()
+        .with::<&Boss>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for boss in bosses {
+        despawn_boss(ecs, state, boss);
+    }
+
+    let projectiles: Vec<Entity> = ecs
+        .query::<()>()
+        .with::<&BossProjectile>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for projectile in projectiles {
+        let _ = ecs.remove_one::<BossProjectile>(projectile);
+        state
+            .deletion_events
+            .push(DeletionEvent::Entity { entity: projectile });
+        state
+            .deletion_events
+            .push(DeletionEvent::Physics { entity: projectile });
+    }
+}
@@ -1,25 +1,45 @@
 use hecs::World;
 
-use crate::state::{DeletionEvent, State};
+use crate::{
+    components::{CTransform, FreeToLeavePlayField, HasRigidBody, Shape},
+    state::{DeletionEvent, State},
+    DIMS,
+};
+
+/// anything free to leave the play field is deleted once it is all the way out
+pub fn remove_escaped_entities(ecs: &World, state: &mut State) {
+    for (entity, (ctransform, shape, has_rigid_body)) in ecs
+        .query::<(&CTransform, &Shape, Option<&HasRigidBody>)>()
+        .with::<&FreeToLeavePlayField>()
+        .iter()
+    {
+        let max = ctransform.pos + shape.dims;
+        if max.x >= 0.0
+            && max.y >= 0.0
+            && ctransform.pos.x <= DIMS.x as f32
+            && ctransform.pos.y <= DIMS.y as f32
+        {
+            continue;
+        }
+        state.deletion_events.push(DeletionEvent::Entity { entity });
+        if has_rigid_body.is_some() {
+            state
+                .deletion_events
+                .push(DeletionEvent::Physics { entity });
+        }
+    }
+}
 
 pub fn process_deletion_events(ecs: &mut World, state: &mut State) {
     for deletion_event in state.deletion_events.iter() {
         match deletion_event {
+            // an entity never outlives its body, or the other way round
             DeletionEvent::Entity { entity } => {
+                state.physics.remove_rigid_body(ecs, *entity);
                 let _ = ecs.take(*entity);
             }
             DeletionEvent::Physics { entity } => {
-                if let Some(rigid_body_handle) = state.physics.get_rigid_body_handle(*entity) {
-                    state.physics.remove_rigid_body_mapping(*entity);
-                    state.physics.rigid_body_set.remove(
-                        rigid_body_handle,
-                        &mut state.physics.island_manager,
-                        &mut state.physics.collider_set,
-                        &mut state.physics.impulse_joint_set,
-                        &mut state.physics.multibody_joint_set,
-                        true, // remove the associated colliders as well
-                    );
-                }
+                state.physics.remove_rigid_body(ecs, *entity);
             }
         }
     }
@@ -0,0 +1,174 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+
+use crate::{
+    components::{
+        Ball, BallEater, Block, Boss, BossProjectile, Bullet, Enemy, Paddle, PowerUp, Wall,
+    },
+    state::State,
+    systems::playing::{boss, enemy_behaviour, lasers, physics, power_ups},
+};
+
+/// what an entity is as far as collisions care
+/// pairs are sorted by role, so a rule's first entity always has the lower role
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CollisionRole {
+    Ball,
+    Bullet,
+    BossProjectile,
+    PowerUp,
+    Block,
+    Boss,
+    Enemy,
+    Paddle,
+    BallEater,
+    Wall,
+}
+
+/// sensors are handled when the overlap starts, solid bodies once they have bounced apart
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollisionPhase {
+    Started,
+    Stopped,
+}
+
+/// whatever the handlers pile up over a step, dealt with after every event is handled
+pub struct CollisionContext {
+    /// centers of blocks destroyed this step
+    pub destroyed_blocks: Vec<Vec2>,
+}
+
+/// called with the two entities in the same order as the rule's roles
+pub type CollisionHandler = fn(&mut World, &mut State, &mut CollisionContext, Entity, Entity);
+
+pub struct CollisionRule {
+    pub roles: (CollisionRole, CollisionRole),
+    pub phase: CollisionPhase,
+    pub handler: CollisionHandler,
+}
+
+/// a new kind of entity gets a role and its rules here
+pub const COLLISION_RULES: &[CollisionRule] = &[
+    CollisionRule {
+        roles: (CollisionRole::Ball, CollisionRole::Block),
+        phase: CollisionPhase::Stopped,
+        handler: physics::ball_hit_block,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Ball, CollisionRole::Paddle),
+        phase: CollisionPhase::Stopped,
+        handler: physics::ball_hit_paddle,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Ball, CollisionRole::BallEater),
+        phase: CollisionPhase::Stopped,
+        handler: physics::ball_lost,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Ball, CollisionRole::Wall),
+        phase: CollisionPhase::Stopped,
+        handler: physics::ball_hit_wall,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Ball, CollisionRole::Boss),
+        phase: CollisionPhase::Stopped,
+        handler: boss::ball_hit_boss,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Ball, CollisionRole::Enemy),
+        phase: CollisionPhase::Stopped,
+        handler: enemy_behaviour::ball_hit_enemy,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Bullet, CollisionRole::Block),
+        phase: CollisionPhase::Started,
+        handler: lasers::bullet_hit_block,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Bullet, CollisionRole::Boss),
+        phase: CollisionPhase::Started,
+        handler: boss::bullet_hit_boss,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Bullet, CollisionRole::Wall),
+        phase: CollisionPhase::Started,
+        handler: lasers::bullet_hit_wall,
+    },
+    CollisionRule {
+        roles: (CollisionRole::BossProjectile, CollisionRole::Paddle),
+        phase: CollisionPhase::Started,
+        handler: boss::projectile_hit_paddle,
+    },
+    CollisionRule {
+        roles: (CollisionRole::PowerUp, CollisionRole::Paddle),
+        phase: CollisionPhase::Started,
+        handler: power_ups::collect_power_up,
+    },
+    CollisionRule {
+        roles: (CollisionRole::PowerUp, CollisionRole::BallEater),
+        phase: CollisionPhase::Started,
+        handler: power_ups::lose_power_up,
+    },
+    CollisionRule {
+        roles: (CollisionRole::Enemy, CollisionRole::Paddle),
+        phase: CollisionPhase::Started,
+        handler: enemy_behaviour::enemy_hit_paddle,
+    },
+];
+
+/// checked in order, so the bottom wall is a ball eater before it is a wall
+pub fn get_collision_role(ecs: &World, entity: Entity) -> Option<CollisionRole> {
+    let is = |found: Result<bool, hecs::NoSuchEntity>| found.unwrap_or(false);
+    if is(ecs.satisfies::<&Ball>(entity)) {
+        Some(CollisionRole::Ball)
+    } else if is(ecs.satisfies::<&Bullet>(entity)) {
+        Some(CollisionRole::Bullet)
+    } else if is(ecs.satisfies::<&BossProjectile>(entity)) {
+        Some(CollisionRole::BossProjectile)
+    } else if is(ecs.satisfies::<&PowerUp>(entity)) {
+        Some(CollisionRole::PowerUp)
+    } else if is(ecs.satisfies::<&Block>(entity)) {
+        Some(CollisionRole::Block)
+    } else if is(ecs.satisfies::<&Boss>(entity)) {
+        Some(CollisionRole::Boss)
+    } else if is(ecs.satisfies::<&Enemy>(entity)) {
+        Some(CollisionRole::Enemy)
+    } else if is(ecs.satisfies::<&Paddle>(entity)) {
+        Some(CollisionRole::Paddle)
+    } else if is(ecs.satisfies::<&BallEater>(entity)) {
+        Some(CollisionRole::BallEater)
+    } else if is(ecs.satisfies::<&Wall>(entity)) {
+        Some(CollisionRole::Wall)
+    } else {
+        None
+    }
+}
+
+/// sorts the pair by role and hands it to the matching rule, if there is one
+pub fn dispatch_collision(
+    ecs: &mut World,
+    state: &mut State,
+    context: &mut CollisionContext,
+    phase: CollisionPhase,
+    entity_a: Entity,
+    entity_b: Entity,
+) {
+    let (Some(role_a), Some(role_b)) = (
+        get_collision_role(ecs, entity_a),
+        get_collision_role(ecs, entity_b),
+    ) else {
+        return;
+    };
+    let (first, second, roles) = if role_a <= role_b {
+        (entity_a, entity_b, (role_a, role_b))
+    } else {
+        (entity_b, entity_a, (role_b, role_a))
+    };
+
+    if let Some(rule) = COLLISION_RULES
+        .iter()
+        .find(|rule| rule.roles == roles && rule.phase == phase)
+    {
+        (rule.handler)(ecs, state, context, first, second);
+    }
+}
@@ -1 +1,115 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+use rand::Rng;
 
+use crate::{
+    audio_playing::AudioCommand,
+    components::{CTransform, Enemy, EnemyGate, OwnedBy, Physics, Shape, WantsToGoTo},
+    entity_archetypes::{spawn_enemy, ENEMY_SHAPE},
+    state::{DeletionEvent, State, FRAMES_PER_SECOND},
+    systems::playing::{collisions::CollisionContext, scoring::add_points},
+    timer::EnemySpawnTimer,
+    DIMS, TS_RATIO,
+};
+
+pub const ENEMY_SPAWN_INTERVAL: u32 = 8 * FRAMES_PER_SECOND;
+pub const MAX_ENEMIES: usize = 3;
+pub const ENEMY_SPEED: f32 = 30.0 * (1.0 / TS_RATIO);
+pub const ENEMY_POINTS: u32 = 100;
+/// how far down the field each new target is, so enemies slowly drift out the bottom
+const ENEMY_DRIFT_MIN: f32 = 10.0;
+const ENEMY_DRIFT_MAX: f32 = 30.0;
+/// close enough to a target to pick the next one
+const ENEMY_ARRIVE_DISTANCE: f32 = 2.0;
+
+/// each gate lets an enemy in when its timer runs out, if there is room for one
+pub fn spawn_enemies(ecs: &mut World, state: &mut State) {
+    let mut enemy_count = ecs.query::<&Enemy>().iter().count();
+    let mut spawns: Vec<Vec2> = Vec::new();
+    for (_, (ctransform, shape, enemy_spawn_timer)) in ecs
+        .query::<(&CTransform, &Shape, &mut EnemySpawnTimer)>()
+        .with::<&EnemyGate>()
+        .iter()
+    {
+        enemy_spawn_timer.step();
+        if enemy_spawn_timer.get_countdown() > 0 || enemy_count >= MAX_ENEMIES {
+            continue;
+        }
+        enemy_spawn_timer.reset();
+        enemy_count += 1;
+        spawns.push(Vec2::new(
+            ctransform.pos.x + (shape.dims.x - ENEMY_SHAPE.x) / 2.0,
+            ctransform.pos.y + shape.dims.y,
+        ));
+    }
+
+    for pos in spawns {
+        let target = get_next_enemy_target(state, pos);
+        spawn_enemy(ecs, state, pos, target);
+    }
+}
+
+/// enemies head for their target, picking a new one a little further down once they get there
+pub fn steer_enemies(ecs: &mut World, state: &mut State) {
+    for (_, (ctransform, physics, wants_to_go_to)) in ecs
+        .query_mut::<(&CTransform, &mut Physics, &mut WantsToGoTo)>()
+        .with::<&Enemy>()
+    {
+        if ctransform.pos.distance(wants_to_go_to.pos) < ENEMY_ARRIVE_DISTANCE {
+            wants_to_go_to.pos = get_next_enemy_target(state, ctransform.pos);
+        }
+        physics.vel = (wants_to_go_to.pos - ctransform.pos).normalize_or_zero() * ENEMY_SPEED;
+    }
+}
+
+/// somewhere across the field and further down, past the bottom eventually
+fn get_next_enemy_target(state: &mut State, from: Vec2) -> Vec2 {
+    let x = state.rng.gen_range(0.0..DIMS.x as f32 - ENEMY_SHAPE.x);
+    let y = from.y + state.rng.gen_range(ENEMY_DRIFT_MIN..ENEMY_DRIFT_MAX);
+    Vec2::new(x, y)
+}
+
+/// the ball bounces off enemies and breaks them, its owner gets the points
+pub fn ball_hit_enemy(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    ball: Entity,
+    enemy: Entity,
+) {
+    state
+        .audio_command_buffer
+        .push(AudioCommand::BallBlockBounce);
+    if let Ok(owner) = ecs.get::<&OwnedBy>(ball).map(|owned_by| owned_by.owner) {
+        add_points(ecs, owner, ENEMY_POINTS);
+    }
+    destroy_enemy(ecs, state, enemy);
+}
+
+/// the paddle flattens any enemy it touches
+pub fn enemy_hit_paddle(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    enemy: Entity,
+    paddle: Entity,
+) {
+    state
+        .audio_command_buffer
+        .push(AudioCommand::BallSturdyBlockBounce);
+    add_points(ecs, paddle, ENEMY_POINTS);
+    destroy_enemy(ecs, state, enemy);
+}
+
+/// an enemy can touch the ball and the paddle in the same step, it only breaks once
+fn destroy_enemy(ecs: &mut World, state: &mut State, enemy: Entity) {
+    if ecs.remove_one::<Enemy>(enemy).is_err() {
+        return;
+    }
+    state
+        .deletion_events
+        .push(DeletionEvent::Entity { entity: enemy });
+    state
+        .deletion_events
+        .push(DeletionEvent::Physics { entity: enemy });
+}
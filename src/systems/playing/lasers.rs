@@ -0,0 +1,88 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+
+use crate::{
+    audio_playing::AudioCommand,
+    components::{ActivePowerUps, Bullet, CTransform, Paddle, PowerUpType, Shape, StrongBlock},
+    entity_archetypes::{spawn_bullet, BULLET_SHAPE},
+    state::{DeletionEvent, State},
+    systems::playing::{
+        collisions::CollisionContext, physics::damage_block, power_ups::get_stacks,
+    },
+    timer::LaserFireTimer,
+    TS_RATIO,
+};
+
+pub const LASER_FIRE_INTERVAL: u32 = (20.0 * TS_RATIO) as u32;
+pub const BULLET_VEL: f32 = 300.0 * (1.0 / TS_RATIO);
+
+/// paddles with lasers active fire a bolt from each edge while confirm is held
+pub fn fire_lasers(ecs: &mut World, state: &mut State) {
+    let mut bolts: Vec<(Vec2, Entity)> = Vec::new();
+    for (entity, (ctransform, shape, active_power_ups, laser_fire_timer)) in ecs
+        .query::<(&CTransform, &Shape, &ActivePowerUps, &mut LaserFireTimer)>()
+        .with::<&Paddle>()
+        .iter()
+    {
+        laser_fire_timer.step();
+        if !state.playing_inputs.confirm
+            || get_stacks(active_power_ups, PowerUpType::Lasers) == 0
+            || laser_fire_timer.get_countdown() > 0
+        {
+            continue;
+        }
+        laser_fire_timer.reset();
+
+        let y = ctransform.pos.y - BULLET_SHAPE.y;
+        bolts.push((Vec2::new(ctransform.pos.x + 1.0, y), entity));
+        bolts.push((
+            Vec2::new(ctransform.pos.x + shape.dims.x - 1.0 - BULLET_SHAPE.x, y),
+            entity,
+        ));
+    }
+
+    if !bolts.is_empty() {
+        state.audio_command_buffer.push(AudioCommand::LaserFire);
+    }
+    for (pos, owner) in bolts {
+        spawn_bullet(ecs, state, pos, Vec2::new(0.0, -BULLET_VEL), owner);
+    }
+}
+
+/// a bolt only ever hits one thing, even if it touched several this step
+pub fn spend_bullet(ecs: &mut World, state: &mut State, bullet: Entity) {
+    let _ = ecs.remove_one::<Bullet>(bullet);
+    state
+        .deletion_events
+        .push(DeletionEvent::Entity { entity: bullet });
+    state
+        .deletion_events
+        .push(DeletionEvent::Physics { entity: bullet });
+}
+
+pub fn bullet_hit_block(
+    ecs: &mut World,
+    state: &mut State,
+    context: &mut CollisionContext,
+    bullet: Entity,
+    block: Entity,
+) {
+    if ecs.satisfies::<&StrongBlock>(block).unwrap_or(false) {
+        state
+            .audio_command_buffer
+            .push(AudioCommand::BallSturdyBlockBounce);
+    } else if let Some(block_center) = damage_block(ecs, state, bullet, block) {
+        context.destroyed_blocks.push(block_center);
+    }
+    spend_bullet(ecs, state, bullet);
+}
+
+pub fn bullet_hit_wall(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    bullet: Entity,
+    _wall: Entity,
+) {
+    spend_bullet(ecs, state, bullet);
+}
@@ -1,7 +1,18 @@
+pub mod attachment;
+pub mod ball_speed;
+pub mod ball_watchdog;
+pub mod bomb_ball;
+pub mod boss;
 pub mod cleanup;
+pub mod collisions;
 pub mod enemy_behaviour;
 pub mod input_processing;
+pub mod lasers;
 pub mod physics;
+pub mod piercing_ball;
+pub mod power_ups;
 pub mod rendering;
+pub mod scoring;
 pub mod state_changing;
 pub mod util;
+pub mod warp;
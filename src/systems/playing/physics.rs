@@ -1,69 +1,97 @@
 use glam::Vec2;
-use hecs::World;
-use nalgebra::Vector2;
-use rapier2d::prelude::RigidBodyHandle;
+use hecs::{Entity, World};
 
 use crate::audio_playing::AudioCommand;
 use crate::components::{
-    Ball, BallEater, Block, CTransform, FreeToLeavePlayField, HasRigidBody, Health, Paddle,
-    Physics, PositionManaged, Shape, StrongBlock, VelocityManaged, Wall,
+    AttachedTo, Ball, BallSpeed, Block, CTransform, FreeToLeavePlayField, HasRigidBody, Health,
+    Paddle, Physics, Piercing, PositionHistory, PositionManaged, Shape, StrongBlock,
+    VelocityManaged,
 };
 use crate::game_mode_transitions::BASE_PADDLE_SHAPE;
-use crate::physics_engine::{m2p, p2m};
+use crate::levels::get_level;
+use crate::physics_hooks::ColliderTag;
 use crate::state::{DeletionEvent, State};
+use crate::systems::playing::collisions::{dispatch_collision, CollisionContext, CollisionPhase};
+use crate::systems::playing::{attachment, ball_speed, bomb_ball, power_ups, scoring};
 use crate::{DIMS, TS_RATIO};
 
 pub fn sync_ecs_to_physics(ecs: &World, state: &mut State) {
     // velocity managed
-    for (entity, physics) in ecs
-        .query::<&mut Physics>()
-        .with::<(&HasRigidBody, &VelocityManaged)>()
+    for (_, (physics, has_rigid_body)) in ecs
+        .query::<(&mut Physics, &HasRigidBody)>()
+        .with::<&VelocityManaged>()
         .iter()
     {
-        if let Some(body) = state.physics.get_rigid_body_handle(entity) {
-            if let Some(rigid_body) = state.physics.rigid_body_set.get_mut(body) {
-                let vel = Vector2::new(p2m(physics.vel.x), p2m(physics.vel.y));
-                rigid_body.set_linvel(vel, true);
-            }
-        }
+        state.physics.set_vel(has_rigid_body.handle, physics.vel);
     }
 
     // position managed
-    for (entity, (ctransform, shape)) in ecs
-        .query::<(&mut CTransform, &Shape)>()
-        .with::<(&HasRigidBody, &PositionManaged)>()
+    for (_, (ctransform, shape, has_rigid_body)) in ecs
+        .query::<(&mut CTransform, &Shape, &HasRigidBody)>()
+        .with::<&PositionManaged>()
         .iter()
     {
-        if let Some(body) = state.physics.get_rigid_body_handle(entity) {
-            if let Some(rigid_body) = state.physics.rigid_body_set.get_mut(body) {
-                let center = ctransform.pos + shape.dims / 2.0;
-                let pos = Vector2::new(p2m(center.x), p2m(center.y));
-                rigid_body.set_position(pos.into(), true);
-            }
-        }
+        let center = ctransform.pos + shape.dims / 2.0;
+        state.physics.set_center(has_rigid_body.handle, center);
     }
 }
-const ANGLE_45_IN_RAD: f32 = std::f32::consts::PI / 3.0;
-const BALL_VEL: f32 = 200.0 * (1.0 / TS_RATIO);
-pub fn set_ball_to_angle(ecs: &World, state: &mut State) {
-    for (entity, physics) in ecs
-        .query::<&mut Physics>()
+/// the ball never travels flatter or steeper than this, in radians from horizontal
+pub const MIN_BALL_ANGLE: f32 = std::f32::consts::PI / 9.0;
+pub const MAX_BALL_ANGLE: f32 = std::f32::consts::PI * 5.0 / 12.0;
+/// how much a moving paddle tilts the bounce, in radians per pixel per step
+pub const PADDLE_ENGLISH: f32 = 0.04;
+/// steps of paddle movement averaged for english
+pub const PADDLE_HISTORY_LENGTH: usize = 6;
+
+/// the current level's base ball speed, scaled to the timestep
+pub fn get_ball_speed(state: &State) -> f32 {
+    get_level(&state.levels, state.level).ball_speed * (1.0 / TS_RATIO)
+}
+
+/// keeps every free ball at its speed and within the allowed angles
+/// whatever direction the last bounce left it going
+pub fn constrain_ball_velocities(ecs: &World, state: &mut State) {
+    for (_, (physics, ball_speed)) in ecs
+        .query::<(&mut Physics, Option<&BallSpeed>)>()
         .with::<(&HasRigidBody, &Ball)>()
+        .without::<&AttachedTo>()
         .iter()
     {
-        if let Some(_body) = state.physics.get_rigid_body_handle(entity) {
-            // physics.vel = physics.vel.normalize() * BALL_VEL;
+        let speed = ball_speed.map_or(get_ball_speed(state), |ball_speed| ball_speed.speed);
+        physics.vel = clamp_ball_angle(physics.vel) * speed;
+    }
+}
+
+/// the direction of vel, pulled back within MIN_BALL_ANGLE and MAX_BALL_ANGLE of horizontal
+pub fn clamp_ball_angle(vel: Vec2) -> Vec2 {
+    // a stopped ball carries on upwards
+    let x_sign = if vel.x < 0.0 { -1.0 } else { 1.0 };
+    let y_sign = if vel.y > 0.0 { 1.0 } else { -1.0 };
+    let angle = vel.y.abs().atan2(vel.x.abs());
+    let angle = angle.clamp(MIN_BALL_ANGLE, MAX_BALL_ANGLE);
+    Vec2::new(angle.cos() * x_sign, angle.sin() * y_sign)
+}
 
-            let x_sign = physics.vel.x.signum();
-            let y_sign = physics.vel.y.signum();
+pub fn record_position_histories(ecs: &mut World) {
+    for (_, (ctransform, history)) in ecs.query_mut::<(&CTransform, &mut PositionHistory)>() {
+        history.positions.push_back(ctransform.pos);
+        while history.positions.len() > PADDLE_HISTORY_LENGTH {
+            history.positions.pop_front();
+        }
+    }
+}
 
-            physics.vel.x = ANGLE_45_IN_RAD.cos() * BALL_VEL * x_sign;
-            physics.vel.y = ANGLE_45_IN_RAD.sin() * BALL_VEL * y_sign;
+/// average pixels per step over the recorded history
+pub fn get_history_velocity(history: &PositionHistory) -> Vec2 {
+    match (history.positions.front(), history.positions.back()) {
+        (Some(oldest), Some(newest)) if history.positions.len() > 1 => {
+            (*newest - *oldest) / (history.positions.len() - 1) as f32
         }
+        _ => Vec2::ZERO,
     }
 }
 
-/// Collision events are emptied here so dont check collisions in step before this is called
+/// Collision events are replaced here so dont check collisions in step before this is called
 pub fn step_physics(ecs: &World, state: &mut State) {
     state.physics.step();
 
@@ -72,56 +100,35 @@ pub fn step_physics(ecs: &World, state: &mut State) {
     //////////////////////////////////////////////////////////////////
 
     // first for positions
-    for (entity, (ctransform, shape)) in ecs
-        .query::<(&mut CTransform, &Shape)>()
-        .with::<&HasRigidBody>()
+    for (_, (ctransform, shape, has_rigid_body)) in ecs
+        .query::<(&mut CTransform, &Shape, &HasRigidBody)>()
         .without::<&PositionManaged>()
         .iter()
     {
-        if let Some(body) = state.physics.get_rigid_body_handle(entity) {
-            if let Some(rigid_body) = state.physics.rigid_body_set.get(body) {
-                let center = rigid_body.position().translation.vector;
-                let rot = rigid_body.position().rotation.angle();
-                let pos = Vec2::new(
-                    m2p(center.x) - shape.dims.x / 2.0,
-                    m2p(center.y) - shape.dims.y / 2.0,
-                );
-                ctransform.pos = pos;
-                ctransform.rot = Vec2::new(rot.cos(), rot.sin());
-            }
+        if let Some(center) = state.physics.get_center(has_rigid_body.handle) {
+            ctransform.pos = center - shape.dims / 2.0;
+            // bodies never turn
+            ctransform.rot = Vec2::X;
         }
     }
 
     // now for velocities
-    for (entity, physics) in ecs.query::<&mut Physics>().with::<&HasRigidBody>().iter() {
-        if let Some(body) = state.physics.get_rigid_body_handle(entity) {
-            if let Some(rigid_body) = state.physics.rigid_body_set.get(body) {
-                let vel = *rigid_body.linvel();
-                physics.vel = Vec2::new(m2p(vel.x), m2p(vel.y));
-            }
+    for (_, (physics, has_rigid_body)) in ecs.query::<(&mut Physics, &HasRigidBody)>().iter() {
+        if let Some(vel) = state.physics.get_vel(has_rigid_body.handle) {
+            physics.vel = vel;
         }
     }
 
     // paddle specifically
-    for (entity, (ctransform, shape)) in ecs
-        .query::<(&mut CTransform, &Shape)>()
+    for (_, (ctransform, shape, has_rigid_body)) in ecs
+        .query::<(&mut CTransform, &Shape, &HasRigidBody)>()
         .with::<&Paddle>()
         .iter()
     {
-        if let Some(body) = state.physics.get_rigid_body_handle(entity) {
-            if let Some(rigid_body) = state.physics.rigid_body_set.get(body) {
-                let center = rigid_body.position().translation.vector;
-                ctransform.pos.x = m2p(center.x) - shape.dims.x / 2.0;
-                ctransform.pos.y = m2p(center.y) - shape.dims.y / 2.0;
-            }
+        if let Some(center) = state.physics.get_center(has_rigid_body.handle) {
+            ctransform.pos = center - shape.dims / 2.0;
         }
     }
-
-    // store all collision events for later iteration
-    state.physics.collision_events.clear();
-    while let Ok(event) = state.physics.collision_recv.try_recv() {
-        state.physics.collision_events.push(event);
-    }
 }
 
 pub fn constantly_resize_paddle(ecs: &mut World, state: &mut State) {
@@ -130,297 +137,234 @@ pub fn constantly_resize_paddle(ecs: &mut World, state: &mut State) {
         BASE_PADDLE_SHAPE.y,
     );
     println!("new shape: {:?}", new_shape);
-    for (entity, shape) in ecs.query::<&mut Shape>().with::<&Paddle>().iter() {
+    for (_, (shape, has_rigid_body)) in ecs
+        .query::<(&mut Shape, &HasRigidBody)>()
+        .with::<&Paddle>()
+        .iter()
+    {
         shape.dims = new_shape;
-
-        if let Some(body) = state.physics.get_rigid_body_handle(entity) {
-            if let Some(rigid_body) = state.physics.rigid_body_set.get(body) {
-                for collider_handle in rigid_body.colliders().iter() {
-                    if let Some(collider) = state.physics.collider_set.get_mut(*collider_handle) {
-                        collider.set_shape(rapier2d::geometry::ColliderShape::cuboid(
-                            p2m(new_shape.x / 2.0),
-                            p2m(new_shape.y / 2.0),
-                        ));
-                    }
-                }
-            }
-        }
+        state.physics.set_dims(has_rigid_body.handle, new_shape);
     }
 }
 
-#[allow(clippy::option_map_unit_fn)]
 pub fn respond_to_collisions(ecs: &mut World, state: &mut State) {
-    let collision_events = state.physics.collision_events.clone();
-    for event in collision_events {
-        if event.started() {
-            continue;
-        }
-        // Fetch the entities associated with the colliders involved in this collision event
-        // also fetch the rigid body handles
-        let mut rigid_body_handle_a: Option<RigidBodyHandle> = None;
-        let mut rigid_body_handle_b: Option<RigidBodyHandle> = None;
-
-        let entity_a = state
-            .physics
-            .collider_set
-            .get(event.collider1())
-            .and_then(|collider_a| collider_a.parent())
-            .and_then(|rigid_body_a| {
-                rigid_body_handle_a = Some(rigid_body_a);
-                state
-                    .physics
-                    .get_entity_from_rigid_body_handle(rigid_body_a)
-            });
-
-        let entity_b = state
-            .physics
-            .collider_set
-            .get(event.collider2())
-            .and_then(|collider_b| collider_b.parent())
-            .and_then(|rigid_body_b| {
-                rigid_body_handle_b = Some(rigid_body_b);
-                state
-                    .physics
-                    .get_entity_from_rigid_body_handle(rigid_body_b)
-            });
-
-        // if there are entities associated with those colliders
-        // respond to the collision depending on the entity types and properties
-        if let (Some(entity_a), Some(entity_b)) = (entity_a, entity_b) {
-            println!("Collision between {:?} and {:?}", entity_a, entity_b);
-            // case: a is ball and b is block
-            if ecs.satisfies::<&Ball>(entity_a).unwrap_or(false) {
-                println!("a is a ball");
-                // check if b is block and BallUnbreakable
-                if ecs
-                    .satisfies::<(&Block, &StrongBlock)>(entity_b)
-                    .unwrap_or(false)
-                {
-                    state
-                        .audio_command_buffer
-                        .push(AudioCommand::BallBlockBounce);
-                    continue;
-                }
-
-                // reduce block health on hit
-                if let Ok((_block, health)) = ecs.query_one_mut::<(&Block, &mut Health)>(entity_b) {
-                    match health.hp {
-                        0 => {}
-                        1 => {
-                            health.hp -= 1;
-                            state
-                                .audio_command_buffer
-                                .push(AudioCommand::BallBlockBounce);
-                            state
-                                .deletion_events
-                                .push(DeletionEvent::Entity { entity: entity_b });
-                            state
-                                .deletion_events
-                                .push(DeletionEvent::Physics { entity: entity_b });
-                            continue;
-                        }
-                        _ => {
-                            health.hp -= 1;
-                            state
-                                .audio_command_buffer
-                                .push(AudioCommand::BallSturdyBlockBounce);
-                            continue;
-                        }
-                    }
-                }
-            }
-
-            // case: a is ball and b is paddle
-            if ecs.satisfies::<&Paddle>(entity_b).unwrap_or(false) {
-                state
-                    .audio_command_buffer
-                    .push(AudioCommand::BallPaddleBounce);
-
-                let mut ball_new_direction: Option<f32> = None;
-
-                // determine if the ball hit the left, middle or right of the paddle
-                // if hit left, set ball velocity to -
-                // if hit right, set ball velocity to +
-                if let Ok(mut res) = ecs.query_one::<(&Paddle, &CTransform, &Shape)>(entity_b) {
-                    if let Some((_, ctransform, shape)) = res.get() {
-                        let paddle_start = ctransform.pos.x;
-                        let paddle_end = paddle_start + shape.dims.x;
-
-                        // get ball position
-                        if let Ok(mut res) = ecs.query_one::<(&Ball, &CTransform, &Shape)>(entity_a)
-                        {
-                            if let Some((_, ctransform, shape)) = res.get() {
-                                let ball_center = ctransform.pos.x + shape.dims.x / 2.0;
-
-                                // if ball_pos is in the left 3rd, set ball velocity to -
-                                let paddle_left_third_end =
-                                    paddle_start + (paddle_end - paddle_start) / 3.0;
-                                if ball_center > paddle_start && ball_center < paddle_left_third_end
-                                {
-                                    ball_new_direction = Some(-1.0);
-                                }
-                                let paddle_right_third_start =
-                                    paddle_end - (paddle_end - paddle_start) / 3.0;
-                                if ball_center > paddle_right_third_start
-                                    && ball_center < paddle_end
-                                {
-                                    ball_new_direction = Some(1.0);
-                                }
-                            }
-                        }
-                    }
-                }
-
-                if let Some(new_direction) = ball_new_direction {
-                    if let Ok((_, physics)) = ecs.query_one_mut::<(&Ball, &mut Physics)>(entity_a) {
-                        physics.vel.x = BALL_VEL * new_direction;
-                        physics.vel.y = -BALL_VEL;
-                    }
-                }
-
-                continue;
-            }
-
-            // case: a is ball and b is balleater
-            if ecs.satisfies::<&BallEater>(entity_b).unwrap_or(false) {
-                state.audio_command_buffer.push(AudioCommand::BallDrop);
-                state
-                    .deletion_events
-                    .push(DeletionEvent::Entity { entity: entity_a });
-                state
-                    .deletion_events
-                    .push(DeletionEvent::Physics { entity: entity_a });
-                continue;
-            }
-
-            // case: a is ball and b is wall
-            if ecs.satisfies::<&Wall>(entity_b).unwrap_or(false) {
-                state
-                    .audio_command_buffer
-                    .push(AudioCommand::BallWallBounce);
-                continue;
-            }
-
-            ////////////////    MIRRORED CASES    ////////////////
-            // case: b is ball and a is block
-            if ecs.satisfies::<&Ball>(entity_b).unwrap_or(false) {
-                println!("b is a ball");
-                // check if a is block and BallUnbreakable
-                if ecs
-                    .satisfies::<(&Block, &StrongBlock)>(entity_a)
-                    .unwrap_or(false)
-                {
-                    state
-                        .audio_command_buffer
-                        .push(AudioCommand::BallBlockBounce);
-                    continue;
-                }
-
-                // reduce block health on hit
-                if let Ok((_, health)) = ecs.query_one_mut::<(&Block, &mut Health)>(entity_a) {
-                    println!("a is a block");
-
-                    // reduce block health on hit
-                    match health.hp {
-                        0 => {}
-                        1 => {
-                            health.hp -= 1;
-                            state
-                                .audio_command_buffer
-                                .push(AudioCommand::BallBlockBounce);
-                            state
-                                .deletion_events
-                                .push(DeletionEvent::Entity { entity: entity_a });
-                            state
-                                .deletion_events
-                                .push(DeletionEvent::Physics { entity: entity_a });
-                            continue;
-                        }
-                        _ => {
-                            health.hp -= 1;
-                            state
-                                .audio_command_buffer
-                                .push(AudioCommand::BallSturdyBlockBounce);
-                            continue;
-                        }
-                    }
-                }
-            }
-
-            // case: b is ball and a is paddle
-            if ecs.satisfies::<&Paddle>(entity_a).unwrap_or(false) {
-                state
-                    .audio_command_buffer
-                    .push(AudioCommand::BallPaddleBounce);
-
-                let mut ball_new_direction: Option<f32> = None;
+    let contact_events = state.physics.get_contact_events().to_vec();
+    let mut context = CollisionContext {
+        destroyed_blocks: Vec::new(),
+    };
+    for event in contact_events {
+        let phase = if event.started {
+            CollisionPhase::Started
+        } else {
+            CollisionPhase::Stopped
+        };
+        dispatch_collision(
+            ecs,
+            state,
+            &mut context,
+            phase,
+            event.entity_a,
+            event.entity_b,
+        );
+    }
 
-                // determine if the ball hit the left, middle or right of the paddle
-                // if hit left, set ball velocity to -
-                // if hit right, set ball velocity to +
-                if let Ok(mut res) = ecs.query_one::<(&Paddle, &CTransform, &Shape)>(entity_a) {
-                    if let Some((_, ctransform, shape)) = res.get() {
-                        let paddle_start = ctransform.pos.x;
-                        let paddle_end = paddle_start + shape.dims.x;
+    // destroyed blocks get a chance to drop a power up
+    for block_center in context.destroyed_blocks {
+        power_ups::maybe_drop_power_up(ecs, state, block_center);
+    }
+}
 
-                        // get ball position
-                        if let Ok(mut res) = ecs.query_one::<(&Ball, &CTransform, &Shape)>(entity_b)
-                        {
-                            if let Some((_, ctransform, shape)) = res.get() {
-                                let ball_center = ctransform.pos.x + shape.dims.x / 2.0;
+pub fn ball_hit_block(
+    ecs: &mut World,
+    state: &mut State,
+    context: &mut CollisionContext,
+    ball: Entity,
+    block: Entity,
+) {
+    if let Ok(block_y) = ecs
+        .get::<&CTransform>(block)
+        .map(|ctransform| ctransform.pos.y)
+    {
+        ball_speed::reach_block_row(ecs, state, ball, block_y);
+    }
+    // a bomb ball goes off on whatever block it hits first, strong or not
+    if bomb_ball::try_detonate_ball(ecs, ball) {
+        let block_center = ecs
+            .query_one_mut::<(&CTransform, &Shape)>(block)
+            .map(|(ctransform, shape)| ctransform.pos + shape.dims / 2.0);
+        if let Ok(block_center) = block_center {
+            bomb_ball::explode(ecs, state, context, ball, block_center);
+            return;
+        }
+    }
+    if ecs.satisfies::<&StrongBlock>(block).unwrap_or(false) {
+        state
+            .audio_command_buffer
+            .push(AudioCommand::BallBlockBounce);
+        return;
+    }
+    // a piercing ball doesn't bounce, so it has to break the block on the way through
+    if ecs.satisfies::<&Piercing>(ball).unwrap_or(false) {
+        if let Ok(health) = ecs.query_one_mut::<&mut Health>(block) {
+            health.hp = health.hp.min(1);
+        }
+    }
+    if let Some(block_center) = damage_block(ecs, state, ball, block) {
+        context.destroyed_blocks.push(block_center);
+    }
+}
 
-                                // if ball_pos is in the left 3rd, set ball velocity to -
-                                let paddle_left_third_end =
-                                    paddle_start + (paddle_end - paddle_start) / 3.0;
-                                if ball_center > paddle_start && ball_center < paddle_left_third_end
-                                {
-                                    ball_new_direction = Some(-1.0);
-                                }
-                                let paddle_right_third_start =
-                                    paddle_end - (paddle_end - paddle_start) / 3.0;
-                                if ball_center > paddle_right_third_start
-                                    && ball_center < paddle_end
-                                {
-                                    ball_new_direction = Some(1.0);
-                                }
-                            }
-                        }
-                    }
-                }
+pub fn ball_hit_paddle(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    ball: Entity,
+    paddle: Entity,
+) {
+    state
+        .audio_command_buffer
+        .push(AudioCommand::BallPaddleBounce);
+    scoring::reset_combo(ecs, paddle);
+    ball_speed::count_paddle_hit(ecs, state, ball);
+
+    // the bounce angle itself was decided during the step, by the physics hooks
+    attachment::try_catch_ball(ecs, state, ball, paddle);
+}
 
-                if let Some(new_direction) = ball_new_direction {
-                    if let Ok((_, physics)) = ecs.query_one_mut::<(&Ball, &mut Physics)>(entity_b) {
-                        physics.vel.x = BALL_VEL * new_direction;
-                        physics.vel.y = -BALL_VEL;
-                    }
-                }
+/// where a ball bounces off the paddle, offset is -1 at the left end to 1 at the right
+/// the further from the paddle's center, the flatter the bounce
+/// english is extra tilt from a moving paddle dragging the ball along with it
+pub fn get_paddle_bounce_direction(offset: f32, english: f32, incoming: Vec2) -> Vec2 {
+    let max_tilt = std::f32::consts::FRAC_PI_2 - MIN_BALL_ANGLE;
+    let tilt = (offset.clamp(-1.0, 1.0) * max_tilt + english).clamp(-max_tilt, max_tilt);
+    // a dead center hit keeps the ball heading the way it came in
+    let x = if tilt == 0.0 {
+        incoming.x.signum() * f32::EPSILON
+    } else {
+        tilt.sin()
+    };
+    clamp_ball_angle(Vec2::new(x, -tilt.cos()))
+}
 
-                continue;
+/// tells the physics what the gameplay rules need to know about each entity
+pub fn sync_collider_tags(ecs: &World, state: &mut State) {
+    for (entity, (has_rigid_body, ball, piercing, block, strong_block, history)) in ecs
+        .query::<(
+            &HasRigidBody,
+            Option<&Ball>,
+            Option<&Piercing>,
+            Option<&Block>,
+            Option<&StrongBlock>,
+            Option<&PositionHistory>,
+        )>()
+        .iter()
+    {
+        let mut tag = ColliderTag::default();
+        if ball.is_some() {
+            tag.flags |= ColliderTag::BALL;
+            if piercing.is_some() {
+                tag.flags |= ColliderTag::PIERCING_BALL;
             }
+        }
+        if block.is_some() && strong_block.is_none() {
+            tag.flags |= ColliderTag::BREAKABLE_BLOCK;
+        }
+        if ecs.satisfies::<&Paddle>(entity).unwrap_or(false) {
+            tag.flags |= ColliderTag::PADDLE;
+            tag.english = history.map_or(0.0, |history| {
+                get_history_velocity(history).x * PADDLE_ENGLISH
+            });
+        }
+        state.physics.set_tag(has_rigid_body.handle, tag);
+    }
+}
 
-            // case: b is ball and a is balleater
-            if ecs.satisfies::<&BallEater>(entity_a).unwrap_or(false) {
-                state.audio_command_buffer.push(AudioCommand::BallDrop);
-                state
-                    .deletion_events
-                    .push(DeletionEvent::Entity { entity: entity_b });
-                state
-                    .deletion_events
-                    .push(DeletionEvent::Physics { entity: entity_b });
-                continue;
-            }
+/// a ball that touches the eater stops being a ball straight away, so it is only lost once
+/// and check_for_level_lost sees whether it was the last one
+pub fn ball_lost(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    ball: Entity,
+    _ball_eater: Entity,
+) {
+    if ecs.remove_one::<Ball>(ball).is_err() {
+        return;
+    }
+    state.audio_command_buffer.push(AudioCommand::BallDrop);
+    state.run_stats.balls_lost += 1;
+    state
+        .deletion_events
+        .push(DeletionEvent::Entity { entity: ball });
+    state
+        .deletion_events
+        .push(DeletionEvent::Physics { entity: ball });
+}
 
-            // case: b is ball and a is wall
-            if ecs.satisfies::<&Wall>(entity_a).unwrap_or(false) {
-                state
-                    .audio_command_buffer
-                    .push(AudioCommand::BallWallBounce);
-                continue;
-            }
-        };
+pub fn ball_hit_wall(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    ball: Entity,
+    wall: Entity,
+) {
+    state
+        .audio_command_buffer
+        .push(AudioCommand::BallWallBounce);
+
+    // the top wall sits entirely above the play field
+    let is_top_wall = ecs
+        .query_one_mut::<(&CTransform, &Shape)>(wall)
+        .map_or(false, |(ctransform, shape)| {
+            ctransform.pos.y + shape.dims.y <= 1.0
+        });
+    if is_top_wall {
+        ball_speed::reach_top_wall(ecs, state, ball);
     }
 }
 
+/// takes one hp off a block, queueing its deletion when it runs out
+/// the hitter's owner is credited with the hit
+/// returns the block's center if it was destroyed
+pub fn damage_block(
+    ecs: &mut World,
+    state: &mut State,
+    hitter: Entity,
+    block: Entity,
+) -> Option<Vec2> {
+    let Ok((_, health, ctransform, shape)) =
+        ecs.query_one_mut::<(&Block, &mut Health, &CTransform, &Shape)>(block)
+    else {
+        return None;
+    };
+    let destroyed = match health.hp {
+        0 => return None,
+        1 => {
+            health.hp -= 1;
+            state
+                .audio_command_buffer
+                .push(AudioCommand::BallBlockBounce);
+            state
+                .deletion_events
+                .push(DeletionEvent::Entity { entity: block });
+            state
+                .deletion_events
+                .push(DeletionEvent::Physics { entity: block });
+            state.run_stats.level_mut(state.level).blocks_broken += 1;
+            Some(ctransform.pos + shape.dims / 2.0)
+        }
+        _ => {
+            health.hp -= 1;
+            state
+                .audio_command_buffer
+                .push(AudioCommand::BallSturdyBlockBounce);
+            None
+        }
+    };
+    scoring::score_block_hit(ecs, state, hitter, block, destroyed.is_some());
+    destroyed
+}
+
 pub fn boundary_checking(ecs: &World, _state: &mut State) {
     for (_, (ctransform, shape)) in ecs
         .query::<(&mut CTransform, &Shape)>()
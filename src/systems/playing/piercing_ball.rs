@@ -0,0 +1,44 @@
+use hecs::{Entity, World};
+
+use crate::{
+    components::{ActivePowerUps, Ball, HasRigidBody, OwnedBy, Piercing, PowerUpType},
+    state::State,
+    systems::playing::power_ups::get_stacks,
+};
+
+/// balls pierce for as long as their owner has the power up
+/// sync_collider_tags passes Piercing on to the physics hooks
+pub fn update_piercing_balls(ecs: &mut World, state: &mut State) {
+    let mut changes: Vec<(Entity, bool)> = Vec::new();
+    for (entity, (owned_by, piercing)) in ecs
+        .query::<(&OwnedBy, Option<&Piercing>)>()
+        .with::<&Ball>()
+        .iter()
+    {
+        let owner_piercing = ecs
+            .get::<&ActivePowerUps>(owned_by.owner)
+            .map_or(false, |active_power_ups| {
+                get_stacks(&active_power_ups, PowerUpType::PiercingBall) > 0
+            });
+        if owner_piercing != piercing.is_some() {
+            changes.push((entity, owner_piercing));
+        }
+    }
+
+    for (entity, piercing) in changes {
+        if piercing {
+            let _ = ecs.insert_one(entity, Piercing);
+        } else {
+            let _ = ecs.remove_one::<Piercing>(entity);
+        }
+
+        let Ok(body) = ecs
+            .get::<&HasRigidBody>(entity)
+            .map(|has_rigid_body| has_rigid_body.handle)
+        else {
+            continue;
+        };
+        // rapier's ccd would stop the ball at the first block in its way, hooks or not
+        state.physics.set_ccd(body, !piercing);
+    }
+}
@@ -0,0 +1,319 @@
+use glam::Vec2;
+use hecs::{Entity, World};
+use rand::{seq::IteratorRandom, Rng};
+use raylib::prelude::Color;
+use strum::IntoEnumIterator;
+
+use crate::{
+    audio_playing::AudioCommand,
+    components::{
+        ActivePowerUp, ActivePowerUps, AttachedTo, Ball, BallSpeed, CTransform, GrabZone,
+        HasRigidBody, OwnedBy, Paddle, Physics, PowerUp, PowerUpType, Shape,
+    },
+    entity_archetypes::{spawn_ball, spawn_powerup, BALL_SHAPE, POWER_UP_SHAPE},
+    game_mode_transitions::BASE_PADDLE_SHAPE,
+    state::{DeletionEvent, State, FRAMES_PER_SECOND},
+    systems::playing::{
+        attachment::GRAB_ZONE_RADIUS,
+        ball_speed,
+        collisions::CollisionContext,
+        scoring::{add_points, POWER_UP_PICKUP_POINTS},
+    },
+    TS_RATIO,
+};
+
+pub const POWER_UP_DROP_CHANCE: f64 = 0.15;
+pub const POWER_UP_FALL_SPEED: f32 = 40.0 * (1.0 / TS_RATIO);
+/// the most balls one paddle can have in play at once
+pub const MAX_BALLS: usize = 9;
+/// how far each split ball turns away from the one it split from
+pub const BALL_SPLIT_ANGLE: f32 = std::f32::consts::PI / 8.0;
+/// how far along its own way each split ball starts, so it isn't spawned on top of the others
+pub const BALL_SPLIT_OFFSET: f32 = 3.0;
+
+/// how picking up a power up that is already active is handled
+pub enum PowerUpStacking {
+    /// applied once on pickup, never tracked as active
+    Instant,
+    /// picking it up again just resets the timer
+    Refresh,
+    /// each pickup adds a stack up to the max, and resets the timer
+    Stack { max_stacks: u32 },
+}
+
+pub fn get_power_up_stacking(power_up_type: PowerUpType) -> PowerUpStacking {
+    match power_up_type {
+        PowerUpType::Enlarge | PowerUpType::Shrink => PowerUpStacking::Stack { max_stacks: 2 },
+        // these change the speed the balls carry with them
+        PowerUpType::SpeedUp | PowerUpType::SlowDown | PowerUpType::BallSplit => {
+            PowerUpStacking::Instant
+        }
+        PowerUpType::Lasers
+        | PowerUpType::PiercingBall
+        | PowerUpType::Catch
+        | PowerUpType::Break => PowerUpStacking::Refresh,
+        // each stack is one explosive impact, spent by the ball
+        PowerUpType::BombBall => PowerUpStacking::Stack { max_stacks: 3 },
+    }
+}
+
+/// duration in frames
+pub fn get_power_up_duration(power_up_type: PowerUpType) -> u32 {
+    let seconds = match power_up_type {
+        PowerUpType::Enlarge | PowerUpType::Shrink => 20.0,
+        PowerUpType::SpeedUp | PowerUpType::SlowDown | PowerUpType::BallSplit => 0.0,
+        PowerUpType::Lasers => 10.0,
+        PowerUpType::PiercingBall => 10.0,
+        PowerUpType::Catch => 20.0,
+        PowerUpType::Break => 15.0,
+        PowerUpType::BombBall => 30.0,
+    };
+    (seconds * FRAMES_PER_SECOND as f32) as u32
+}
+
+/// power ups that cancel each other out when picked up
+pub fn get_opposing_power_up(power_up_type: PowerUpType) -> Option<PowerUpType> {
+    match power_up_type {
+        PowerUpType::Enlarge => Some(PowerUpType::Shrink),
+        PowerUpType::Shrink => Some(PowerUpType::Enlarge),
+        _ => None,
+    }
+}
+
+pub fn get_power_up_color(power_up_type: PowerUpType) -> Color {
+    match power_up_type {
+        PowerUpType::Enlarge => Color::BLUE,
+        PowerUpType::Shrink => Color::PURPLE,
+        PowerUpType::SpeedUp => Color::ORANGE,
+        PowerUpType::SlowDown => Color::GREEN,
+        PowerUpType::BallSplit => Color::SKYBLUE,
+        PowerUpType::Lasers => Color::RED,
+        PowerUpType::Catch => Color::LIME,
+        PowerUpType::BombBall => Color::GRAY,
+        PowerUpType::PiercingBall => Color::GOLD,
+        PowerUpType::Break => Color::PINK,
+    }
+}
+
+/// called when a block is destroyed, sometimes drops a capsule from where the block was
+pub fn maybe_drop_power_up(ecs: &mut World, state: &mut State, block_center: Vec2) {
+    if !state.rng.gen_bool(POWER_UP_DROP_CHANCE) {
+        return;
+    }
+    let Some(power_up_type) = PowerUpType::iter().choose(&mut state.rng) else {
+        return;
+    };
+    let pos = block_center - POWER_UP_SHAPE / 2.0;
+    let vel = Vec2::new(0.0, POWER_UP_FALL_SPEED);
+    spawn_powerup(ecs, state, pos, vel, power_up_type);
+}
+
+pub fn collect_power_up(
+    ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    power_up_entity: Entity,
+    paddle: Entity,
+) {
+    let Ok(power_up_type) = ecs
+        .get::<&PowerUp>(power_up_entity)
+        .map(|power_up| power_up.power_up_type)
+    else {
+        return;
+    };
+    state.audio_command_buffer.push(AudioCommand::PowerUpPickup);
+    add_points(ecs, paddle, POWER_UP_PICKUP_POINTS);
+    state.run_stats.power_ups_collected += 1;
+    apply_power_up(ecs, state, paddle, power_up_type);
+    delete_power_up(state, power_up_entity);
+}
+
+/// the capsule fell past the paddle
+pub fn lose_power_up(
+    _ecs: &mut World,
+    state: &mut State,
+    _context: &mut CollisionContext,
+    power_up_entity: Entity,
+    _ball_eater: Entity,
+) {
+    delete_power_up(state, power_up_entity);
+}
+
+fn delete_power_up(state: &mut State, power_up_entity: Entity) {
+    state.deletion_events.push(DeletionEvent::Entity {
+        entity: power_up_entity,
+    });
+    state.deletion_events.push(DeletionEvent::Physics {
+        entity: power_up_entity,
+    });
+}
+
+/// the single place a picked up power up takes effect on a paddle
+pub fn apply_power_up(
+    ecs: &mut World,
+    state: &mut State,
+    paddle: Entity,
+    power_up_type: PowerUpType,
+) {
+    match get_power_up_stacking(power_up_type) {
+        PowerUpStacking::Instant => {}
+        stacking => {
+            if let Ok(active_power_ups) = ecs.query_one_mut::<&mut ActivePowerUps>(paddle) {
+                add_active_power_up(active_power_ups, power_up_type, stacking);
+            }
+        }
+    }
+
+    match power_up_type {
+        PowerUpType::BallSplit => split_balls(ecs, state, paddle),
+        PowerUpType::SpeedUp => ball_speed::speed_up_owned_balls(ecs, state, paddle),
+        PowerUpType::SlowDown => ball_speed::slow_down_owned_balls(ecs, state, paddle),
+        // everything else is a timed effect, read every frame by the systems it affects
+        PowerUpType::Enlarge
+        | PowerUpType::Shrink
+        | PowerUpType::Lasers
+        | PowerUpType::BombBall
+        | PowerUpType::PiercingBall
+        | PowerUpType::Catch
+        | PowerUpType::Break => {}
+    }
+}
+
+fn add_active_power_up(
+    active_power_ups: &mut ActivePowerUps,
+    power_up_type: PowerUpType,
+    stacking: PowerUpStacking,
+) {
+    if let Some(opposing) = get_opposing_power_up(power_up_type) {
+        active_power_ups
+            .active
+            .retain(|active| active.power_up_type != opposing);
+    }
+
+    let frames_left = get_power_up_duration(power_up_type);
+    if let Some(active) = active_power_ups
+        .active
+        .iter_mut()
+        .find(|active| active.power_up_type == power_up_type)
+    {
+        active.frames_left = frames_left;
+        if let PowerUpStacking::Stack { max_stacks } = stacking {
+            active.stacks = (active.stacks + 1).min(max_stacks);
+        }
+        return;
+    }
+
+    active_power_ups.active.push(ActivePowerUp {
+        power_up_type,
+        frames_left,
+        stacks: 1,
+    });
+}
+
+/// every ball in flight splits into three, fanned out either side of where it was going
+/// no more are spawned once the paddle has MAX_BALLS of its own
+fn split_balls(ecs: &mut World, state: &mut State, paddle: Entity) {
+    let balls: Vec<(Vec2, Vec2, Entity, Option<BallSpeed>)> = ecs
+        .query::<(&CTransform, &Physics, &OwnedBy, Option<&BallSpeed>)>()
+        .with::<&Ball>()
+        .without::<&AttachedTo>()
+        .iter()
+        .filter(|(_, (_, _, owned_by, _))| owned_by.owner == paddle)
+        .map(|(_, (ctransform, physics, owned_by, ball_speed))| {
+            (
+                ctransform.pos,
+                physics.vel,
+                owned_by.owner,
+                ball_speed.copied(),
+            )
+        })
+        .collect();
+    let mut ball_count = ecs
+        .query::<&OwnedBy>()
+        .with::<&Ball>()
+        .iter()
+        .filter(|(_, owned_by)| owned_by.owner == paddle)
+        .count();
+
+    for (pos, vel, owner, ball_speed) in balls {
+        for split_angle in [-BALL_SPLIT_ANGLE, BALL_SPLIT_ANGLE] {
+            if ball_count >= MAX_BALLS {
+                return;
+            }
+            ball_count += 1;
+            let split_vel = Vec2::from_angle(split_angle).rotate(vel);
+            // spawn_ball takes the center, the ctransform has the top left
+            let center = pos + BALL_SHAPE / 2.0 + split_vel.normalize_or_zero() * BALL_SPLIT_OFFSET;
+            let ball = spawn_ball(ecs, state, center, split_vel, owner);
+            // the new ball carries on at the speed of the one it split from
+            if let Some(ball_speed) = ball_speed {
+                let _ = ecs.insert_one(ball, ball_speed);
+            }
+        }
+    }
+}
+
+/// counts down active power ups and keeps the paddle size in line with them
+pub fn step_power_ups(ecs: &mut World, state: &mut State) {
+    for (_, active_power_ups) in ecs.query_mut::<&mut ActivePowerUps>() {
+        for active in active_power_ups.active.iter_mut() {
+            active.frames_left = active.frames_left.saturating_sub(1);
+        }
+        active_power_ups
+            .active
+            .retain(|active| active.frames_left > 0 && active.stacks > 0);
+    }
+
+    // catch mode is a grab zone on the paddle for as long as the power up lasts
+    let mut grab_zone_changes: Vec<(Entity, bool)> = Vec::new();
+    for (entity, (active_power_ups, grab_zone)) in ecs
+        .query::<(&ActivePowerUps, Option<&GrabZone>)>()
+        .with::<&Paddle>()
+        .iter()
+    {
+        let catching = get_stacks(active_power_ups, PowerUpType::Catch) > 0;
+        if catching != grab_zone.is_some() {
+            grab_zone_changes.push((entity, catching));
+        }
+    }
+    for (entity, catching) in grab_zone_changes {
+        if catching {
+            let _ = ecs.insert_one(
+                entity,
+                GrabZone {
+                    radius: GRAB_ZONE_RADIUS,
+                },
+            );
+        } else {
+            let _ = ecs.remove_one::<GrabZone>(entity);
+        }
+    }
+
+    for (_, (shape, active_power_ups, has_rigid_body)) in ecs
+        .query::<(&mut Shape, &ActivePowerUps, &HasRigidBody)>()
+        .with::<&Paddle>()
+        .iter()
+    {
+        let width = BASE_PADDLE_SHAPE.x * get_paddle_width_multiplier(active_power_ups);
+        if shape.dims.x == width {
+            continue;
+        }
+        shape.dims.x = width;
+        state.physics.set_dims(has_rigid_body.handle, shape.dims);
+    }
+}
+
+pub fn get_stacks(active_power_ups: &ActivePowerUps, power_up_type: PowerUpType) -> u32 {
+    active_power_ups
+        .active
+        .iter()
+        .find(|active| active.power_up_type == power_up_type)
+        .map(|active| active.stacks)
+        .unwrap_or(0)
+}
+
+pub fn get_paddle_width_multiplier(active_power_ups: &ActivePowerUps) -> f32 {
+    let enlarge = get_stacks(active_power_ups, PowerUpType::Enlarge) as i32;
+    let shrink = get_stacks(active_power_ups, PowerUpType::Shrink) as i32;
+    1.5_f32.powi(enlarge) * 0.7_f32.powi(shrink)
+}
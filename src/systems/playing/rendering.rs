@@ -1,21 +1,35 @@
 use glam::Vec2;
 use hecs::World;
-use rapier2d::prelude::*;
 use raylib::prelude::Color;
 
 use crate::{
     components::{
-        Ball, BallEater, Block, CTransform, Health, Paddle, Physics, Shape, StrongBlock, Wall,
+        ActivePowerUps, Ball, BallEater, Block, Boss, BossProjectile, Bullet, CTransform, Combo,
+        Enemy, EnemyGate, Explosion, Health, LookAt, Paddle, Physics, PowerUp, PowerUpType, Score,
+        Shape, StrongBlock, Wall, WarpGate,
     },
-    physics_engine::m2p,
+    levels::get_level,
     render_commands::RenderCommand,
     state::State,
+    systems::playing::{
+        bomb_ball::EXPLOSION_FRAMES,
+        power_ups::{get_power_up_color, get_power_up_duration},
+        scoring::get_combo_multiplier,
+    },
     DIMS,
 };
 
 pub fn render(ecs: &World, state: &mut State) {
     // render_physics(state);
 
+    state
+        .render_command_buffer
+        .push(RenderCommand::SolidRectangle {
+            pos: Vec2::ZERO,
+            dims: DIMS.as_vec2(),
+            color: get_level(&state.levels, state.level).background,
+        });
+
     let mut cursor = Vec2::new(20.0, 20.0);
     for (_, physics) in ecs.query::<&Physics>().with::<&Ball>().iter() {
         state.render_command_buffer.push(RenderCommand::Text {
@@ -37,6 +51,12 @@ pub fn render(ecs: &World, state: &mut State) {
                 color = Color::RED;
             }
         }
+        // an open warp gate flashes
+        if let Ok(warp_gate) = ecs.get::<&WarpGate>(entity) {
+            if warp_gate.open && (state.t as u32 / 8) % 2 == 0 {
+                color = get_power_up_color(PowerUpType::Break);
+            }
+        }
         state
             .render_command_buffer
             .push(RenderCommand::SolidRectangle {
@@ -46,6 +66,21 @@ pub fn render(ecs: &World, state: &mut State) {
             });
     }
 
+    // the gates enemies come in through are dark gaps in the top wall
+    for (_, (ctransform, shape)) in ecs
+        .query::<(&CTransform, &Shape)>()
+        .with::<&EnemyGate>()
+        .iter()
+    {
+        state
+            .render_command_buffer
+            .push(RenderCommand::SolidRectangle {
+                pos: ctransform.pos,
+                dims: shape.dims,
+                color: Color::DARKGRAY,
+            });
+    }
+
     // render every player as a paddle
     for (_, (_, ctransform, shape)) in ecs.query::<(&Paddle, &CTransform, &Shape)>().iter() {
         state.render_command_buffer.push(RenderCommand::Paddle {
@@ -69,6 +104,114 @@ pub fn render(ecs: &World, state: &mut State) {
         })
     }
 
+    // render the boss, with its hp bar along the top of the field
+    for (_, (boss, health, ctransform, shape, look_at)) in ecs
+        .query::<(&Boss, &Health, &CTransform, &Shape, Option<&LookAt>)>()
+        .iter()
+    {
+        state.render_command_buffer.push(RenderCommand::Boss {
+            pos: ctransform.pos,
+            dims: shape.dims,
+            facing: look_at.map_or(Vec2::Y, |look_at| look_at.facing),
+            flashing: boss.flash_frames > 0,
+        });
+        state.render_command_buffer.push(RenderCommand::HpBar {
+            pos: Vec2::new(DIMS.x as f32 / 4.0, 4.0),
+            dims: Vec2::new(DIMS.x as f32 / 2.0, 3.0),
+            fraction: health.hp as f32 / boss.max_hp.max(1) as f32,
+            color: Color::RED,
+        });
+    }
+
+    // render the boss's shots
+    for (_, (ctransform, shape)) in ecs
+        .query::<(&CTransform, &Shape)>()
+        .with::<&BossProjectile>()
+        .iter()
+    {
+        state.render_command_buffer.push(RenderCommand::Circle {
+            pos: ctransform.pos + shape.dims / 2.0,
+            radius: shape.dims.x / 2.0,
+            color: Color::RED,
+        });
+    }
+
+    // render bomb blasts, swelling out and cooling from yellow to red
+    for (_, (explosion, ctransform)) in ecs.query::<(&Explosion, &CTransform)>().iter() {
+        let progress = 1.0 - explosion.frames_left as f32 / EXPLOSION_FRAMES as f32;
+        let color = if progress < 0.5 {
+            Color::YELLOW
+        } else {
+            Color::RED
+        };
+        state.render_command_buffer.push(RenderCommand::Blast {
+            pos: ctransform.pos,
+            radius: explosion.radius * (0.5 + progress / 2.0),
+            color,
+        });
+    }
+
+    // render falling power up capsules
+    for (_, (power_up, ctransform, shape)) in ecs.query::<(&PowerUp, &CTransform, &Shape)>().iter()
+    {
+        state.render_command_buffer.push(RenderCommand::PowerUp {
+            pos: ctransform.pos,
+            dims: shape.dims,
+            color: get_power_up_color(power_up.power_up_type),
+        })
+    }
+
+    // render active power ups as bars in the bottom left, shrinking as they run out
+    let mut cursor = Vec2::new(4.0, DIMS.y as f32 - 6.0);
+    for (_, active_power_ups) in ecs.query::<&ActivePowerUps>().iter() {
+        for active in active_power_ups.active.iter() {
+            let fraction =
+                active.frames_left as f32 / get_power_up_duration(active.power_up_type) as f32;
+            let color = get_power_up_color(active.power_up_type);
+            for stack in 0..active.stacks {
+                state
+                    .render_command_buffer
+                    .push(RenderCommand::SolidRectangle {
+                        pos: cursor + Vec2::new(stack as f32 * 3.0, 0.0),
+                        dims: Vec2::new(2.0, 2.0),
+                        color,
+                    });
+            }
+            state
+                .render_command_buffer
+                .push(RenderCommand::SolidRectangle {
+                    pos: cursor + Vec2::new(10.0, 0.0),
+                    dims: Vec2::new(20.0 * fraction, 2.0),
+                    color,
+                });
+            cursor.y -= 4.0;
+        }
+    }
+
+    // render enemies
+    for (_, (ctransform, shape)) in ecs.query::<(&CTransform, &Shape)>().with::<&Enemy>().iter() {
+        state.render_command_buffer.push(RenderCommand::Circle {
+            pos: ctransform.pos + shape.dims / 2.0,
+            radius: shape.dims.x / 2.0,
+            color: Color::ORANGE,
+        });
+    }
+
+    // render laser bolts
+    for (_, (ctransform, shape)) in ecs
+        .query::<(&CTransform, &Shape)>()
+        .with::<&Bullet>()
+        .iter()
+    {
+        state
+            .render_command_buffer
+            .push(RenderCommand::SolidRectangle {
+                pos: ctransform.pos,
+                dims: shape.dims,
+                color: Color::RED,
+            });
+    }
+
     // render ball
     for (_, (_, ctransform, shape)) in ecs.query::<(&Ball, &CTransform, &Shape)>().iter() {
         state.render_command_buffer.push(RenderCommand::Ball {
@@ -86,43 +229,56 @@ pub fn render(ecs: &World, state: &mut State) {
         size,
         color: Color::WHITE,
     });
+
+    // score and combo multiplier in the bottom left
+    for (_, (score, combo)) in ecs.query::<(&Score, &Combo)>().iter() {
+        let multiplier = get_combo_multiplier(combo);
+        let text = if multiplier > 1 {
+            format!("Score: {} x{}", score.score, multiplier)
+        } else {
+            format!("Score: {}", score.score)
+        };
+        state.render_command_buffer.push(RenderCommand::Text {
+            pos: Vec2::new(40.0, DIMS.y as f32 - 20.0),
+            text,
+            size,
+            color: Color::WHITE,
+        });
+    }
+
+    // and the lives left next to the level
+    let cursor = Vec2::new(DIMS.x as f32 - 100.0, DIMS.y as f32 - 20.0);
+    state.render_command_buffer.push(RenderCommand::Text {
+        pos: cursor,
+        text: format!("Lives: {}", state.lives),
+        size,
+        color: Color::WHITE,
+    });
 }
 
 pub fn render_physics(state: &mut State) {
     // Render colliders
-    for (_, collider) in state.physics.collider_set.iter() {
-        let center = collider.position().translation.vector;
-        let shape = collider.shape();
-        let shape_type = shape.shape_type();
-
-        if let ShapeType::Cuboid = shape_type {
-            let cuboid = shape.as_cuboid().unwrap();
-            let tl = center + -cuboid.half_extents;
-            let size = cuboid.half_extents * 2.0;
-
-            let ppos = Vec2::new(m2p(tl.x), m2p(tl.y));
-            let psize = Vec2::new(m2p(size.x), m2p(size.y));
-            state.render_command_buffer.push(RenderCommand::Block {
-                pos: ppos,
-                dims: psize,
-                color: Color::RED, // or any color you prefer for debug
-                hp: 1,
-                ball_unbreakable: false,
+    for body in state.physics.get_bodies() {
+        let (Some(center), Some(dims)) =
+            (state.physics.get_center(body), state.physics.get_dims(body))
+        else {
+            continue;
+        };
+        state.render_command_buffer.push(RenderCommand::Block {
+            pos: center - dims / 2.0,
+            dims,
+            color: Color::RED, // or any color you prefer for debug
+            hp: 1,
+            ball_unbreakable: false,
+        });
+
+        // and which way it's heading
+        if let Some(vel) = state.physics.get_vel(body) {
+            state.render_command_buffer.push(RenderCommand::Line {
+                start: center,
+                end: center + vel.normalize_or_zero() * 10.0,
+                color: Color::GREEN, // or any color you prefer for debug
             });
         }
     }
-
-    // Render rigid bodies (Optional, if you need to distinguish them)
-    for (_, rigid_body) in state.physics.rigid_body_set.iter() {
-        let pos = rigid_body.position().translation.vector;
-        let rot = rigid_body.position().rotation.angle();
-
-        let ppos = Vec2::new(m2p(pos.x), m2p(pos.y));
-        let prot = Vec2::new(rot.cos(), rot.sin());
-        state.render_command_buffer.push(RenderCommand::Line {
-            start: ppos,
-            end: ppos + prot * 10.0,
-            color: Color::GREEN, // or any color you prefer for debug
-        });
-    }
 }
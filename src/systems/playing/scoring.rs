@@ -0,0 +1,89 @@
+use hecs::{Entity, World};
+
+use crate::{
+    components::{Block, Combo, OwnedBy, Score},
+    levels::{MULTI_HIT_COLOR_INDEX, UNBREAKABLE_COLOR_INDEX},
+    state::State,
+};
+
+pub const POWER_UP_PICKUP_POINTS: u32 = 1000;
+/// every this many consecutive hits adds one to the multiplier
+pub const COMBO_HITS_PER_MULTIPLIER: u32 = 5;
+pub const MAX_COMBO_MULTIPLIER: u32 = 4;
+
+/// white is worth 50, each colour after it 10 more
+/// silver takes several hits so it scales with the level, gold can't be broken
+pub fn get_block_points(color_index: u32, level: u32) -> u32 {
+    match color_index {
+        0 | UNBREAKABLE_COLOR_INDEX => 0,
+        MULTI_HIT_COLOR_INDEX => 50 * level,
+        _ => 40 + color_index * 10,
+    }
+}
+
+pub fn get_combo_multiplier(combo: &Combo) -> u32 {
+    (1 + combo.hits / COMBO_HITS_PER_MULTIPLIER).min(MAX_COMBO_MULTIPLIER)
+}
+
+/// whoever owns the hitter gets the combo hit, and the points if the block broke
+pub fn score_block_hit(
+    ecs: &mut World,
+    state: &mut State,
+    hitter: Entity,
+    block: Entity,
+    destroyed: bool,
+) {
+    let Some(player) = get_owner(ecs, hitter) else {
+        return;
+    };
+    let Ok(color_index) = ecs.get::<&Block>(block).map(|block| block.color_index) else {
+        return;
+    };
+
+    let mut multiplier = 1;
+    for (_, (score, combo)) in ecs.query_mut::<(&Score, &mut Combo)>() {
+        if score.owner == player {
+            combo.hits += 1;
+            multiplier = get_combo_multiplier(combo);
+            state.run_stats.longest_combo = state.run_stats.longest_combo.max(combo.hits);
+        }
+    }
+
+    if destroyed {
+        add_points(
+            ecs,
+            player,
+            get_block_points(color_index, state.level) * multiplier,
+        );
+    }
+}
+
+/// touching the paddle ends the combo
+pub fn reset_combo(ecs: &mut World, player: Entity) {
+    for (_, (score, combo)) in ecs.query_mut::<(&Score, &mut Combo)>() {
+        if score.owner == player {
+            combo.hits = 0;
+        }
+    }
+}
+
+pub fn add_points(ecs: &mut World, player: Entity, points: u32) {
+    for (_, score) in ecs.query_mut::<&mut Score>() {
+        if score.owner == player {
+            score.score += points;
+        }
+    }
+}
+
+pub fn get_total_score(ecs: &World) -> u32 {
+    ecs.query::<&Score>()
+        .iter()
+        .map(|(_, score)| score.score)
+        .sum()
+}
+
+fn get_owner(ecs: &World, entity: Entity) -> Option<Entity> {
+    ecs.get::<&OwnedBy>(entity)
+        .ok()
+        .map(|owned_by| owned_by.owner)
+}
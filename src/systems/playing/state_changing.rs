@@ -1,7 +1,8 @@
 use crate::{
     audio_playing::AudioCommand,
-    components::{Ball, Block, StrongBlock},
-    state::{GameMode, State},
+    components::{Ball, Block, Boss, StrongBlock},
+    state::{GameMode, State, EXTRA_LIFE_SCORE_INTERVAL, MAX_LIVES},
+    systems::playing::scoring::get_total_score,
 };
 use hecs::World;
 
@@ -12,15 +13,42 @@ pub fn check_for_level_complete(ecs: &World, state: &mut State) {
         .iter()
         .next()
         .is_none()
+        && ecs.query::<&Boss>().iter().next().is_none()
     {
         state.next_game_mode = Some(GameMode::LevelComplete);
         state.audio_command_buffer.push(AudioCommand::LevelWin);
     }
 }
 
+/// losing the last ball costs a life, losing the last life ends the game
 pub fn check_for_level_lost(ecs: &World, state: &mut State) {
+    // clearing the last block with the last ball still counts as a win
+    if state.next_game_mode.is_some() {
+        return;
+    }
     if ecs.query::<&Ball>().iter().next().is_none() {
+        lose_life(state);
+    }
+}
+
+/// the level starts over, or the game ends if that was the last life
+pub fn lose_life(state: &mut State) {
+    state.lives = state.lives.saturating_sub(1);
+    state.audio_command_buffer.push(AudioCommand::LevelLost);
+    if state.lives == 0 {
+        state.next_game_mode = Some(GameMode::GameOver);
+    } else {
         state.next_game_mode = Some(GameMode::PrepareLevel);
-        // state.audio_command_buffer.push(AudioCommand::LevelStart);
+    }
+}
+
+pub fn award_extra_lives(ecs: &World, state: &mut State) {
+    let score = get_total_score(ecs);
+    while score >= state.next_extra_life_score {
+        state.next_extra_life_score += EXTRA_LIFE_SCORE_INTERVAL;
+        if state.lives < MAX_LIVES {
+            state.lives += 1;
+            state.audio_command_buffer.push(AudioCommand::ExtraLife);
+        }
     }
 }
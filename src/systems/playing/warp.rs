@@ -0,0 +1,90 @@
+use hecs::World;
+
+use crate::{
+    audio_playing::AudioCommand,
+    components::{ActivePowerUps, CTransform, HasRigidBody, Paddle, PowerUpType, Shape, WarpGate},
+    physics_backend::CollisionGroups,
+    state::{GameMode, State},
+    systems::playing::{power_ups::get_stacks, scoring::add_points},
+    DIMS,
+};
+
+/// the gate spans the paddle's height in the right wall
+pub const WARP_GATE_TOP: f32 = 136.0;
+pub const WARP_GATE_HEIGHT: f32 = 20.0;
+pub const WARP_BONUS_POINTS: u32 = 10_000;
+
+/// the gate is open for as long as any paddle has Break active
+/// an open gate only stops the ball, so nothing else is held back by it
+pub fn update_warp_gates(ecs: &mut World, state: &mut State) {
+    let open = ecs
+        .query::<&ActivePowerUps>()
+        .with::<&Paddle>()
+        .iter()
+        .any(|(_, active_power_ups)| get_stacks(active_power_ups, PowerUpType::Break) > 0);
+
+    for (_, (warp_gate, has_rigid_body)) in ecs.query_mut::<(&mut WarpGate, &HasRigidBody)>() {
+        if warp_gate.open == open {
+            continue;
+        }
+        warp_gate.open = open;
+
+        let groups = if open {
+            CollisionGroups::new(0b0001, 0b0001)
+        } else {
+            CollisionGroups::ALL
+        };
+        state.physics.set_groups(has_rigid_body.handle, groups);
+    }
+}
+
+/// paddles stay between the side walls, unless the warp gate beside them is open
+pub fn constrain_paddles(ecs: &mut World) {
+    let mut gates: Vec<(f32, f32, bool)> = Vec::new();
+    for (_, (ctransform, shape, warp_gate)) in
+        ecs.query::<(&CTransform, &Shape, &WarpGate)>().iter()
+    {
+        gates.push((
+            ctransform.pos.y,
+            ctransform.pos.y + shape.dims.y,
+            warp_gate.open,
+        ));
+    }
+
+    for (_, (ctransform, shape)) in ecs
+        .query_mut::<(&mut CTransform, &Shape)>()
+        .with::<&Paddle>()
+    {
+        let through_gate = gates.iter().any(|(top, bottom, open)| {
+            *open && ctransform.pos.y >= *top && ctransform.pos.y + shape.dims.y <= *bottom
+        });
+        let max_x = if through_gate {
+            DIMS.x as f32
+        } else {
+            DIMS.x as f32 - 1.0 - shape.dims.x
+        };
+        ctransform.pos.x = ctransform.pos.x.clamp(1.0, max_x);
+    }
+}
+
+/// getting a quarter of the paddle into the open gate clears the level with a bonus
+pub fn check_for_warp(ecs: &mut World, state: &mut State) {
+    if state.next_game_mode.is_some() {
+        return;
+    }
+    let warped = ecs
+        .query::<(&CTransform, &Shape)>()
+        .with::<&Paddle>()
+        .iter()
+        .find(|(_, (ctransform, shape))| {
+            ctransform.pos.x + shape.dims.x * 0.75 > DIMS.x as f32 - 1.0
+        })
+        .map(|(entity, _)| entity);
+    let Some(paddle) = warped else {
+        return;
+    };
+
+    add_points(ecs, paddle, WARP_BONUS_POINTS);
+    state.next_game_mode = Some(GameMode::LevelComplete);
+    state.audio_command_buffer.push(AudioCommand::LevelWin);
+}
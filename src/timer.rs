@@ -26,6 +26,9 @@ impl<T> TypedTimer<T> {
     pub fn get_countdown(&self) -> u32 {
         self.timer.countdown
     }
+    pub fn set_countdown(&mut self, countdown: u32) {
+        self.timer.countdown = countdown;
+    }
     pub fn reset(&mut self) {
         self.timer.countdown = self.timer.interval;
     }
@@ -39,6 +42,10 @@ impl<T> TypedTimer<T> {
 pub struct ForAsteroidSpawning;
 pub struct ForGunSpawning;
 pub struct ForEnemySpawning;
+pub struct ForLaserFiring;
+pub struct ForBossFiring;
 pub type AsteroidSpawnTimer = TypedTimer<ForAsteroidSpawning>;
 pub type GunSpawnTimer = TypedTimer<ForGunSpawning>;
 pub type EnemySpawnTimer = TypedTimer<ForEnemySpawning>;
+pub type LaserFireTimer = TypedTimer<ForLaserFiring>;
+pub type BossFireTimer = TypedTimer<ForBossFiring>;
@@ -0,0 +1,149 @@
+use hecs::World;
+
+use hecs_arkanoid::{
+    game_mode_transitions::transition_game_mode,
+    headless::{describe_world, get_autopilot_input, run_headless, step_headless, InputScript},
+    levels::{get_empty_level, FIRST_PLAYED_ROW, PLAYED_ROWS},
+    physics_backend::PhysicsBackendKind,
+    save_game::{parse_snapshot, restore_snapshot, snapshot_to_string, take_snapshot},
+    state::{GameMode, State},
+    systems::playing::scoring::get_total_score,
+};
+
+/// holds the paddle still, launches, then swings it across and back
+const SCRIPT: &str = "\
+0 120 confirm
+400 80
+700 160 confirm
+1000 120
+";
+
+fn play(
+    seed: u64,
+    physics_backend: PhysicsBackendKind,
+    frames: u32,
+    script: &InputScript,
+) -> (World, State) {
+    let mut state = State::new_seeded(seed);
+    state.set_physics_backend(physics_backend);
+    let mut ecs = World::new();
+    run_headless(&mut ecs, &mut state, 1, frames, Some(script));
+    (ecs, state)
+}
+
+#[test]
+fn same_seed_and_script_play_out_the_same() {
+    let script = InputScript::parse(SCRIPT).unwrap();
+    for physics_backend in [PhysicsBackendKind::Rapier, PhysicsBackendKind::SweptAabb] {
+        let (ecs, state) = play(7, physics_backend, 1500, &script);
+        let (ecs_again, state_again) = play(7, physics_backend, 1500, &script);
+        assert_eq!(
+            describe_world(&ecs, &state),
+            describe_world(&ecs_again, &state_again),
+            "{:?} runs diverged",
+            physics_backend
+        );
+    }
+}
+
+#[test]
+fn launching_into_a_lone_block_clears_the_level() {
+    // one block in the middle of the lowest row, right above the paddle
+    let mut level = get_empty_level("lone block".to_string());
+    level.grid[FIRST_PLAYED_ROW + PLAYED_ROWS - 1][5] = 1;
+    let script = InputScript::parse("0 120 confirm\n").unwrap();
+
+    for physics_backend in [PhysicsBackendKind::Rapier, PhysicsBackendKind::SweptAabb] {
+        let mut state = State::new_seeded(1);
+        state.set_physics_backend(physics_backend);
+        state.levels = vec![level.clone()];
+        let mut ecs = World::new();
+        run_headless(&mut ecs, &mut state, 1, 600, Some(&script));
+        assert!(
+            matches!(state.game_mode, GameMode::LevelComplete | GameMode::WinGame),
+            "{:?} ended in {:?}",
+            physics_backend,
+            state.game_mode
+        );
+        // an empty level would count as cleared too, the block has to have been broken
+        assert!(
+            get_total_score(&ecs) > 0,
+            "{:?} scored nothing",
+            physics_backend
+        );
+        assert_eq!(state.lives, 3);
+        // a single straight bounce is nothing the physics should let slip
+        assert!(
+            state.watchdog_events.is_empty(),
+            "{:?} needed the watchdog: {}",
+            physics_backend,
+            state.watchdog_events[0]
+        );
+    }
+}
+
+/// steps on with the autopilot, the way bin/headless does with no script
+fn play_on(ecs: &mut World, state: &mut State, frames: u32) {
+    for frame in 0..frames {
+        transition_game_mode(ecs, state);
+        let input = get_autopilot_input(ecs, frame);
+        state.mouse_screen_pos.x = input.mouse_x;
+        state.playing_inputs.confirm = input.confirm;
+        step_headless(ecs, state);
+    }
+}
+
+/// the world without entity ids or the frame count, which a restored run starts over
+fn describe_play(ecs: &World, state: &State) -> Vec<String> {
+    describe_world(ecs, state)
+        .lines()
+        .filter(|line| !line.starts_with("frame"))
+        .map(|line| match line.split_once(' ') {
+            Some((kind @ ("paddle" | "ball"), rest)) => {
+                format!(
+                    "{} {}",
+                    kind,
+                    rest.split_once(' ').map_or("", |(_, rest)| rest)
+                )
+            }
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn a_restored_save_plays_out_like_the_run_it_was_taken_from() {
+    let script = InputScript::parse(SCRIPT).unwrap();
+    for physics_backend in [PhysicsBackendKind::Rapier, PhysicsBackendKind::SweptAabb] {
+        let (mut ecs, mut state) = play(3, physics_backend, 900, &script);
+        assert_eq!(state.game_mode, GameMode::Playing);
+
+        let saved = snapshot_to_string(&take_snapshot(&ecs, &state));
+        let snapshot = parse_snapshot(&saved).unwrap();
+        assert_eq!(snapshot_to_string(&snapshot), saved);
+
+        let mut resumed_state = State::new_seeded(0);
+        resumed_state.set_physics_backend(physics_backend);
+        let mut resumed_ecs = World::new();
+        restore_snapshot(&mut resumed_ecs, &mut resumed_state, snapshot);
+        resumed_state.next_game_mode = Some(GameMode::Playing);
+
+        play_on(&mut ecs, &mut state, 1200);
+        play_on(&mut resumed_ecs, &mut resumed_state, 1200);
+        // rapier's contacts are rebuilt rather than saved, which can put its balls a rounding error out
+        let compared = |description: Vec<String>| -> Vec<String> {
+            description
+                .into_iter()
+                .filter(|line| {
+                    physics_backend == PhysicsBackendKind::SweptAabb || !line.starts_with("ball")
+                })
+                .collect()
+        };
+        assert_eq!(
+            compared(describe_play(&ecs, &state)),
+            compared(describe_play(&resumed_ecs, &resumed_state)),
+            "{:?} resumed run went its own way",
+            physics_backend
+        );
+    }
+}